@@ -0,0 +1,337 @@
+use lrpar::Span;
+
+use crate::{simple, Bound, Compare, Expr, MatchOp, Value};
+
+// 代码中构造的表达式没有原文，span 为空区间
+fn span() -> Span {
+    Span::new(0, 0)
+}
+
+// 在代码中直接构造条件，不经过字符串解析
+// field("age").gt(3).and(field("name").is_in(["a", "b"]))
+pub fn field(name: &str) -> Field {
+    Field(name.to_string())
+}
+
+#[derive(Clone, Debug)]
+pub struct Field(String);
+
+impl Field {
+    fn cmp<V: Into<Value>>(self, cmp: Compare, value: V) -> Expr {
+        simple(span(), self.0, cmp, value.into())
+    }
+
+    fn text(self, op: MatchOp, text: &str, flags: &str) -> Expr {
+        Expr::Match {
+            span: span(),
+            field: self.0,
+            op,
+            value: Value::Text(text.to_string()),
+            flags: flags.to_string(),
+        }
+    }
+
+    pub fn eq<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Eq, value)
+    }
+
+    pub fn ne<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Ne, value)
+    }
+
+    pub fn gt<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Gt, value)
+    }
+
+    pub fn gte<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Gte, value)
+    }
+
+    pub fn lt<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Lt, value)
+    }
+
+    pub fn lte<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Lte, value)
+    }
+
+    pub fn is_in<I, V>(self, values: I) -> Expr
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Expr::In {
+            span: span(),
+            field: self.0,
+            value: list(values),
+        }
+    }
+
+    pub fn not_in<I, V>(self, values: I) -> Expr
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Expr::NotIn {
+            span: span(),
+            field: self.0,
+            value: list(values),
+        }
+    }
+
+    pub fn belong<I, V>(self, values: I) -> Expr
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Expr::Belong {
+            span: span(),
+            field: self.0,
+            value: list(values),
+        }
+    }
+
+    pub fn no_belong<I, V>(self, values: I) -> Expr
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Expr::NoBelong {
+            span: span(),
+            field: self.0,
+            value: list(values),
+        }
+    }
+
+    pub fn has<I, V>(self, values: I) -> Expr
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Expr::Has {
+            span: span(),
+            field: self.0,
+            value: list(values),
+        }
+    }
+
+    // 正则，同 a ! 'x'
+    pub fn like(self, pattern: &str) -> Expr {
+        Expr::Like {
+            span: span(),
+            field: self.0,
+            value: Value::Text(pattern.to_string()),
+        }
+    }
+
+    pub fn not_like(self, pattern: &str) -> Expr {
+        Expr::NotLike {
+            span: span(),
+            field: self.0,
+            value: Value::Text(pattern.to_string()),
+        }
+    }
+
+    // 带选项的正则，同 a ! 'x' i
+    pub fn regex(self, pattern: &str, flags: &str) -> Expr {
+        self.text(MatchOp::Regex, pattern, flags)
+    }
+
+    pub fn contains(self, text: &str) -> Expr {
+        self.text(MatchOp::Contains, text, "")
+    }
+
+    pub fn starts_with(self, text: &str) -> Expr {
+        self.text(MatchOp::StartsWith, text, "")
+    }
+
+    pub fn ends_with(self, text: &str) -> Expr {
+        self.text(MatchOp::EndsWith, text, "")
+    }
+
+    pub fn ieq(self, text: &str) -> Expr {
+        self.text(MatchOp::IEq, text, "i")
+    }
+
+    pub fn is_null(self) -> Expr {
+        Expr::IsNull {
+            span: span(),
+            field: self.0,
+        }
+    }
+
+    pub fn is_not_null(self) -> Expr {
+        Expr::IsNotNull {
+            span: span(),
+            field: self.0,
+        }
+    }
+
+    // field("age").between(Bound::Inclusive(18.into()), Bound::Exclusive(60.into()))
+    pub fn between(self, lower: Bound, upper: Bound) -> Expr {
+        Expr::Between {
+            span: span(),
+            field: self.0,
+            lower,
+            upper,
+        }
+    }
+
+    // 数组中有元素满足条件，元素中的字段相对于元素
+    pub fn any(self, expr: Expr) -> Expr {
+        Expr::Any {
+            span: span(),
+            field: self.0,
+            expr: Box::new(expr),
+        }
+    }
+
+    pub fn all(self, expr: Expr) -> Expr {
+        Expr::All {
+            span: span(),
+            field: self.0,
+            expr: Box::new(expr),
+        }
+    }
+
+    // field("tags").len().gt(1)
+    pub fn len(self) -> Len {
+        Len(self.0)
+    }
+}
+
+// 字段作为比较的右侧时与另一个字段比较: field("a").gt(field("b"))
+impl From<Field> for Value {
+    fn from(f: Field) -> Self {
+        Value::Field(f.0)
+    }
+}
+
+fn list<I, V>(values: I) -> Value
+where
+    I: IntoIterator<Item = V>,
+    V: Into<Value>,
+{
+    Value::List(values.into_iter().map(Into::into).collect())
+}
+
+#[derive(Clone, Debug)]
+pub struct Len(String);
+
+impl Len {
+    fn cmp<V: Into<Value>>(self, cmp: Compare, value: V) -> Expr {
+        Expr::Len {
+            span: span(),
+            field: self.0,
+            cmp,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Eq, value)
+    }
+
+    pub fn ne<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Ne, value)
+    }
+
+    pub fn gt<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Gt, value)
+    }
+
+    pub fn gte<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Gte, value)
+    }
+
+    pub fn lt<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Lt, value)
+    }
+
+    pub fn lte<V: Into<Value>>(self, value: V) -> Expr {
+        self.cmp(Compare::Lte, value)
+    }
+}
+
+// 与解析结果一致按右结合构造，a.and(b).and(c) 等同于 a && b && c
+impl Expr {
+    pub fn and(self, rhs: Expr) -> Expr {
+        match self {
+            Expr::And {
+                span,
+                lhs,
+                rhs: mid,
+            } => Expr::And {
+                span,
+                lhs,
+                rhs: Box::new(mid.and(rhs)),
+            },
+            lhs => Expr::And {
+                span: span(),
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+
+    pub fn or(self, rhs: Expr) -> Expr {
+        match self {
+            Expr::Or {
+                span,
+                lhs,
+                rhs: mid,
+            } => Expr::Or {
+                span,
+                lhs,
+                rhs: Box::new(mid.or(rhs)),
+            },
+            lhs => Expr::Or {
+                span: span(),
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+}
+
+// !field("a").eq(1)
+impl std::ops::Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Not {
+            span: span(),
+            expr: Box::new(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field;
+    use crate::{yacc_parse as parse, Bound, Expr};
+
+    #[test]
+    fn test_builder() {
+        let expr = field("age")
+            .gt(3)
+            .and(field("name").is_in(["a", "b"]))
+            .or(!field("tags").len().gte(2))
+            .and(field("a").ne(field("b")))
+            .and(field("t").is_not_null());
+        let s = "(age > 3 && name ~ ('a','b') || !(len(tags) >= 2)) && a != b && t ^^ null";
+        assert_eq!(expr.to_string(), s);
+        assert_eq!(parse(s).unwrap().to_string(), s);
+
+        let expr = field("items")
+            .any(field("n").between(Bound::Inclusive(1.into()), Bound::Exclusive(5.into())))
+            .and(field("name").contains("a.b"))
+            .and(field("name").regex("^x", "i"))
+            .and(field("tags").has(["x"]));
+        assert_eq!(
+            expr.to_string(),
+            "any(items, n between [1, 5)) && name contains 'a.b' && name ! '^x' i && tags has 'x'"
+        );
+        assert!(matches!(field("a").like("^x"), Expr::Like { .. }));
+    }
+}
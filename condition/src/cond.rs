@@ -1,13 +1,18 @@
 #![allow(clippy::unnecessary_wraps)]
 
-use lrlex::lrlex_mod;
-use lrpar::{lrpar_mod, Span};
+use chrono::{DateTime, FixedOffset};
+use lrlex::{lrlex_mod, DefaultLexerTypes};
+use lrpar::{lrpar_mod, LexError, LexParseError, Lexeme, NonStreamingLexer, ParseRepair, Span};
+use serde::{Deserialize, Serialize};
 use serde_json::Number;
 
+use crate::{Params, ParseError, ParseErrorKind, Query};
+
 lrlex_mod!("cond.l");
 lrpar_mod!("cond.y");
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Compare {
     Eq,
     Ne,
@@ -17,133 +22,433 @@ pub enum Compare {
     Lte,
 }
 
-#[derive(Clone, Debug)]
-pub enum Expr {
-    And {
-        span: Span,
-        lhs: Box<Expr>,
-        rhs: Box<Expr>,
-    },
-    Or {
-        span: Span,
-        lhs: Box<Expr>,
-        rhs: Box<Expr>,
-    },
-    Eq {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    Ne {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    Gt {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    Gte {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    Lt {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    Lte {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    Like {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    NotLike {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    In {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    NotIn {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    IsNull {
-        span: Span,
-        field: String,
-    },
-    IsNotNull {
-        span: Span,
-        field: String,
-    },
-    Len {
-        span: Span,
-        field: String,
-        cmp: Compare,
-        value: Value,
-    },
-    Belong {
-        span: Span,
-        field: String,
-        value: Value,
-    },
-    NoBelong {
-        span: Span,
-        field: String,
-        value: Value,
+// 每个节点的第一个字段都是 span，统一加上 serde 属性: JSON 中可以省略，为空时不输出
+macro_rules! spanned {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident {
+                    span: Span,
+                    $($(#[$field_meta:meta])* $field:ident: $ty:ty,)*
+                },
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant {
+                    #[serde(
+                        default = "crate::json::no_span",
+                        skip_serializing_if = "crate::json::is_empty"
+                    )]
+                    span: Span,
+                    $($(#[$field_meta])* $field: $ty,)*
+                },
+            )*
+        }
+    };
+}
+
+spanned! {
+    // JSON 中以 op 区分节点类型
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(tag = "op", rename_all = "snake_case")]
+    pub enum Expr {
+        And {
+            span: Span,
+            lhs: Box<Expr>,
+            rhs: Box<Expr>,
+        },
+        Or {
+            span: Span,
+            lhs: Box<Expr>,
+            rhs: Box<Expr>,
+        },
+        Not {
+            span: Span,
+            expr: Box<Expr>,
+        },
+        Eq {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        Ne {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        Gt {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        Gte {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        Lt {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        Lte {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        Like {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        NotLike {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        In {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        NotIn {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        IsNull {
+            span: Span,
+            field: String,
+        },
+        IsNotNull {
+            span: Span,
+            field: String,
+        },
+        Len {
+            span: Span,
+            field: String,
+            cmp: Compare,
+            value: Value,
+        },
+        Belong {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        NoBelong {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+        // 含算术运算的比较，例如 price * qty >= 100、len(tags) + 1 > limit
+        Compare {
+            span: Span,
+            lhs: Operand,
+            cmp: Compare,
+            rhs: Operand,
+        },
+        // 数组元素匹配，子表达式中的字段相对于数组元素，any(lines, price > 10 && qty > 1)
+        Any {
+            span: Span,
+            field: String,
+            expr: Box<Expr>,
+        },
+        // 数组非空且所有元素都满足子表达式，all(lines, qty > 0)
+        All {
+            span: Span,
+            field: String,
+            expr: Box<Expr>,
+        },
+        // 区间，方括号包含端点，圆括号不包含，a between [1, 10)
+        Between {
+            span: Span,
+            field: String,
+            lower: Bound,
+            upper: Bound,
+        },
+        // 字符串匹配，flags 为正则选项 i(忽略大小写)、m(多行)
+        // a ! 'x' i、a contains 'x'、a startswith 'x' i、a ieq 'x'
+        Match {
+            span: Span,
+            field: String,
+            #[serde(rename = "kind")] // op 用于区分节点类型
+            op: MatchOp,
+            value: Value,
+            flags: String,
+        },
+        // a !! 'x' i，与 NotLike 一样只匹配是字符串且不满足正则的字段
+        NotMatch {
+            span: Span,
+            field: String,
+            value: Value,
+            flags: String,
+        },
+        // 数组包含列表中的所有值，tags has 'x'、tags has ('x','y')
+        Has {
+            span: Span,
+            field: String,
+            value: Value,
+        },
+    }
+}
+
+// 除 Regex 外都按字面文本匹配，不解释正则元字符
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOp {
+    Regex,      // !
+    Contains,   // contains
+    StartsWith, // startswith
+    EndsWith,   // endswith
+    #[serde(rename = "ieq")]
+    IEq, // ieq，忽略大小写的相等
+}
+
+impl MatchOp {
+    // 转换为正则，mongodb 与 regex 都支持 \A \z，不受 m 选项影响
+    pub fn pattern(&self, text: &str) -> String {
+        match self {
+            MatchOp::Regex => text.to_string(),
+            MatchOp::Contains => escape(text),
+            MatchOp::StartsWith => format!("\\A{}", escape(text)),
+            MatchOp::EndsWith => format!("{}\\z", escape(text)),
+            MatchOp::IEq => format!("\\A{}\\z", escape(text)),
+        }
+    }
+}
+
+// 转义正则元字符，与 regex::escape 一致
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bound {
+    Inclusive(Value), // [ ]
+    Exclusive(Value), // ( )
+}
+
+impl Bound {
+    pub fn value(&self) -> &Value {
+        match self {
+            Bound::Inclusive(v) | Bound::Exclusive(v) => v,
+        }
+    }
+
+    pub fn is_inclusive(&self) -> bool {
+        matches!(self, Bound::Inclusive(_))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operand {
+    Field(String), // price
+    Value(Value),  // 数字或占位符
+    Len(String),   // len(tags)
+    Call {
+        name: String,
+        args: Vec<Operand>,
+    }, // lower(name)、date_trunc('day', t)
+    Arith {
+        op: ArithOp,
+        lhs: Box<Operand>,
+        rhs: Box<Operand>,
     },
 }
 
-#[derive(Clone, Debug)]
+impl Operand {
+    pub fn arith(op: ArithOp, lhs: Operand, rhs: Operand) -> Self {
+        Operand::Arith {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Value {
     Text(String),   // abc="123"
     Number(Number), // abc=123, abc=1.2
     Bool(bool),
+    DateTime(DateTime<FixedOffset>), // abc>2022-06-13T16:00:00+08:00, abc>@1655136000
     List(Vec<Value>),
     Len(String),
+    Param(String), // abc=:name，通过 Params 绑定
+    Field(String), // abc>efg，与同一条数据的另一个字段比较
     Null,
 }
 
-pub fn yacc_parse<'a, S: ToString + ?Sized>(s: &'a S) -> anyhow::Result<Expr> {
-    let lexerdef = cond_l::lexerdef();
+// 按 default_limits() 限制条件的规模，见 set_default_limits
+pub fn yacc_parse<S: ToString + ?Sized>(s: &S) -> Result<Expr, ParseError> {
+    parse_limited(&s.to_string(), &crate::default_limits())
+}
+
+fn parse_limited(binding: &str, limits: &crate::ParseLimits) -> Result<Expr, ParseError> {
+    limits.check_input(binding)?;
+    let expr = parse(binding)?;
+    expr.check_limits(limits)
+        .map_err(|e| e.with_input(binding))?;
+    Ok(expr)
+}
+
+fn parse(binding: &str) -> Result<Expr, ParseError> {
+    if binding.trim().is_empty() {
+        return Err(
+            ParseError::new(ParseErrorKind::Empty, Span::new(0, binding.len()), "")
+                .with_input(binding),
+        );
+    }
+    parse_query(binding)?
+        .into_expr(binding)
+        .map_err(|e| e.with_input(binding))
+}
 
+// 解析带 order by、limit、offset、select 子句的完整查询，条件和子句都可以省略
+// yacc_parse_query("age > 3 order by name asc, age desc limit 20 offset 40 select name,age")
+pub fn yacc_parse_query<S: ToString + ?Sized>(s: &S) -> Result<Query, ParseError> {
     let binding = s.to_string();
-    let lexer = lexerdef.lexer(&binding);
-    let (res, errs) = cond_y::parse(&lexer);
+    let limits = crate::default_limits();
+    limits.check_input(&binding)?;
+    let query = parse_query(&binding)?;
+    if let Some(expr) = &query.expr {
+        expr.check_limits(&limits)
+            .map_err(|e| e.with_input(&binding))?;
+    }
+    Ok(query)
+}
 
-    let mut errors = vec![];
-    for e in errs {
-        errors.push(format!(
-            "{}, text: \"{}\"",
-            e.pp(&lexer, &cond_y::token_epp),
-            binding
-        ));
+// 同 yacc_parse_query，并绑定条件中的占位符参数
+pub fn yacc_parse_query_with<S: ToString + ?Sized>(
+    s: &S,
+    params: &Params,
+) -> Result<Query, ParseError> {
+    let binding = s.to_string();
+    let mut query = yacc_parse_query(&binding)?;
+    if let Some(expr) = query.expr.take() {
+        let expr = expr.bind(params).map_err(|e| e.with_input(&binding))?;
+        expr.check_limits(&crate::default_limits())
+            .map_err(|e| e.with_input(&binding))?;
+        query.expr = Some(expr);
     }
+    Ok(query)
+}
+
+fn parse_query(binding: &str) -> Result<Query, ParseError> {
+    let lexerdef = cond_l::lexerdef();
+
+    let lexer = lexerdef.lexer(binding);
+    let (res, errs) = cond_y::parse(&lexer);
 
-    if errors.len() > 0 {
-        return Err(anyhow::anyhow!("{}", errors.concat()));
+    // 只返回第一个错误，后续错误往往是错误恢复产生的连带错误
+    if let Some(e) = errs.into_iter().next() {
+        return Err(convert_error(binding, &lexer, e).with_input(binding));
     }
 
     match res {
-        Some(expr) => Ok(expr),
-        None => return Err(anyhow::anyhow!("{}", "Unable to evaluate expression.")),
+        Some(Ok(query)) => Ok(query),
+        Some(Err(e)) => Err(e.with_input(binding)),
+        None => Err(
+            ParseError::new(ParseErrorKind::Syntax, Span::new(0, binding.len()), binding)
+                .with_input(binding),
+        ),
+    }
+}
+
+// 解析并绑定占位符参数
+// yacc_parse_with("name = :name", &Params::new().bind("name", "bill"))
+pub fn yacc_parse_with<S: ToString + ?Sized>(s: &S, params: &Params) -> Result<Expr, ParseError> {
+    yacc_parse_limited(s, params, &crate::default_limits())
+}
+
+// 同 yacc_parse_with，使用指定的限制，绑定参数后再次检查列表长度等
+// yacc_parse_limited(input, &Params::new(), &ParseLimits { max_depth: 8, ..Default::default() })
+pub fn yacc_parse_limited<S: ToString + ?Sized>(
+    s: &S,
+    params: &Params,
+    limits: &crate::ParseLimits,
+) -> Result<Expr, ParseError> {
+    let binding = s.to_string();
+    let expr = parse_limited(&binding, limits)?
+        .bind(params)
+        .map_err(|e| e.with_input(&binding))?;
+    expr.check_limits(limits)
+        .map_err(|e| e.with_input(&binding))?;
+    Ok(expr)
+}
+
+fn convert_error(
+    input: &str,
+    lexer: &dyn NonStreamingLexer<DefaultLexerTypes<u32>>,
+    e: LexParseError<u32, DefaultLexerTypes<u32>>,
+) -> ParseError {
+    match e {
+        LexParseError::LexError(e) => {
+            // 词法错误的 span 为空，取出错位置的字符作为 found
+            let start = e.span().start();
+            let found = input[start..]
+                .chars()
+                .next()
+                .map(|c| c.to_string())
+                .unwrap_or_default();
+            ParseError::new(
+                ParseErrorKind::Lexical,
+                Span::new(start, start + found.len()),
+                &found,
+            )
+        }
+        LexParseError::ParseError(e) => {
+            let span = e.lexeme().span();
+            let mut expected: Vec<String> = vec![];
+            for repairs in e.repairs() {
+                if let Some(ParseRepair::Insert(tidx)) = repairs.first() {
+                    if let Some(name) = cond_y::token_epp(*tidx) {
+                        if !expected.iter().any(|e| e == name) {
+                            expected.push(name.to_string());
+                        }
+                    }
+                }
+            }
+            let kind = if span.is_empty() {
+                ParseErrorKind::UnexpectedEnd
+            } else {
+                ParseErrorKind::Syntax
+            };
+            ParseError::new(kind, span, lexer.span_str(span)).with_expected(expected)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::yacc_parse as parse;
+    use crate::ParseErrorKind;
 
     #[test]
     fn test_base() {
@@ -242,6 +547,23 @@ mod tests {
             Ok(rs) => println!("{:#?}", rs),
             Err(e) => panic!("{}", e),
         }
+
+        // 列表元素可以是任意标量
+        use super::{Expr, Value};
+        match parse("a ~ (true, false, null)") {
+            Ok(Expr::In {
+                value: Value::List(items),
+                ..
+            }) => assert!(matches!(
+                items[..],
+                [Value::Bool(true), Value::Bool(false), Value::Null]
+            )),
+            rs => panic!("unexpected result {:#?}", rs),
+        }
+        assert_eq!(
+            parse("a ~~ (null) && b has true").unwrap().to_string(),
+            "a ~~ (null) && b has true"
+        );
     }
 
     #[test]
@@ -336,4 +658,583 @@ mod tests {
             Err(e) => panic!("{}", e),
         };
     }
+
+    #[test]
+    fn test_parse_error() {
+        // 非法数字返回错误而不是 panic
+        match parse("a = 1.2.3") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::InvalidNumber);
+                assert_eq!((e.span.start(), e.span.end()), (4, 9));
+                assert_eq!(e.render(), "a = 1.2.3\n    ^^^^^ invalid number `1.2.3`");
+            }
+        };
+
+        match parse("a ~ (1,2.2.2)") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::InvalidNumber);
+                assert_eq!(e.found, "2.2.2");
+                assert_eq!((e.span.start(), e.span.end()), (7, 12));
+            }
+        };
+
+        match parse("a = && b = 1") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::Syntax);
+                assert_eq!(e.found, "&&");
+                assert!(!e.expected.is_empty());
+                println!("{}", e.render());
+            }
+        };
+
+        match parse("a = ") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::UnexpectedEnd);
+                println!("{}", e.render());
+            }
+        };
+
+        match parse("a @ 1") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::Lexical);
+                assert_eq!(e.found, "@");
+                assert_eq!(e.render(), "a @ 1\n  ^ lexical error `@`");
+            }
+        };
+
+        match parse("  ") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => assert_eq!(e.kind, ParseErrorKind::Empty),
+        };
+    }
+
+    #[test]
+    fn test_not() {
+        use super::Expr;
+
+        // 取反优先级高于 && 和 ||
+        match parse("!(a=1) && b=2") {
+            Ok(Expr::And { lhs, rhs, .. }) => {
+                assert!(matches!(*lhs, Expr::Not { .. }));
+                assert!(matches!(*rhs, Expr::Eq { .. }));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        match parse("NOT a=1 || b=2") {
+            Ok(Expr::Or { lhs, rhs, .. }) => {
+                assert!(matches!(*lhs, Expr::Not { .. }));
+                assert!(matches!(*rhs, Expr::Eq { .. }));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        match parse("!(a=1 || b=2) && c=3") {
+            Ok(Expr::And { lhs, .. }) => match *lhs {
+                Expr::Not { expr, .. } => assert!(matches!(*expr, Expr::Or { .. })),
+                rs => panic!("unexpected result {:#?}", rs),
+            },
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        match parse("not not (a=1 && b=2)") {
+            Ok(Expr::Not { expr, .. }) => match *expr {
+                Expr::Not { expr, .. } => assert!(matches!(*expr, Expr::And { .. })),
+                rs => panic!("unexpected result {:#?}", rs),
+            },
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 以 not 开头的字段名不受影响
+        match parse("note = 'x' && !(nothing ^ null)") {
+            Ok(rs) => println!("{:#?}", rs),
+            Err(e) => panic!("{}", e),
+        };
+
+        // 只有 ! 表示取反，like 不能用于取反
+        for s in ["like (a = 1)", "LIKE (a = 1 && b = 2)"] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_datetime() {
+        use super::{Expr, Value};
+
+        let cases = [
+            ("t > 2022-06-13T16:00:00+08:00", 1655107200),
+            ("t > 2022-06-13 08:00:00Z", 1655107200),
+            ("t > 2022-06-13T08:00:00.000z", 1655107200),
+            ("t > @1655107200", 1655107200),
+        ];
+        for (sym, ts) in cases {
+            match parse(sym) {
+                Ok(Expr::Gt {
+                    value: Value::DateTime(dt),
+                    ..
+                }) => assert_eq!(dt.timestamp(), ts, "{}", sym),
+                rs => panic!("unexpected result {:#?}", rs),
+            }
+        }
+
+        match parse("t ~ (2022-06-13T16:00:00+08:00,@0)") {
+            Ok(Expr::In {
+                value: Value::List(items),
+                ..
+            }) => assert!(items.iter().all(|v| matches!(v, Value::DateTime(_)))),
+            rs => panic!("unexpected result {:#?}", rs),
+        }
+
+        match parse("t > 2022-13-01T00:00:00Z") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::InvalidDateTime);
+                assert_eq!(e.found, "2022-13-01T00:00:00Z");
+            }
+        };
+    }
+
+    #[test]
+    fn test_literal() {
+        use super::{Expr, Value};
+
+        match parse(r#"a = 'it\'s \"x\"\t中'"#) {
+            Ok(Expr::Eq {
+                value: Value::Text(v),
+                ..
+            }) => assert_eq!(v, "it's \"x\"\t中"),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        for (sym, n) in [
+            ("a = -3", -3.0),
+            ("a = 1.5e3", 1500.0),
+            ("a > -2.5E-1", -0.25),
+        ] {
+            match parse(sym) {
+                Ok(Expr::Eq {
+                    value: Value::Number(v),
+                    ..
+                })
+                | Ok(Expr::Gt {
+                    value: Value::Number(v),
+                    ..
+                }) => assert_eq!(v.as_f64(), Some(n), "{}", sym),
+                rs => panic!("unexpected result {:#?}", rs),
+            }
+        }
+
+        match parse("a ~ ( 'x,y' , \"it's\" )") {
+            Ok(Expr::In {
+                value: Value::List(items),
+                ..
+            }) => assert_eq!(items.len(), 2),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 空列表，与绑定空列表的参数相同
+        match parse("a ~ ()") {
+            Ok(Expr::In {
+                value: Value::List(items),
+                ..
+            }) => assert!(items.is_empty()),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 反引号中的字段名可以包含任意字符
+        match parse(r"`a b\`c` = 1 && `null` ^ null") {
+            Ok(Expr::And { lhs, rhs, .. }) => {
+                assert!(matches!(*lhs, Expr::Eq { field, .. } if field == "a b`c"));
+                assert!(matches!(*rhs, Expr::IsNull { field, .. } if field == "null"));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        for (sym, kind) in [
+            ("a = 1e999", ParseErrorKind::InvalidNumber),
+            ("a = 1.", ParseErrorKind::InvalidNumber),
+            (r"a = '\u12'", ParseErrorKind::InvalidEscape),
+            ("a ~ (,)", ParseErrorKind::Syntax),
+            ("`` = 1", ParseErrorKind::ExpectedField),
+        ] {
+            match parse(sym) {
+                Ok(rs) => panic!("unexpected result {:#?}", rs),
+                Err(e) => assert_eq!(e.kind, kind, "{}", sym),
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_compare() {
+        use super::{Expr, Value};
+
+        // 右侧以数字开头或包含 - 的字段多半是漏写了引号或空格
+        for (s, found) in [
+            ("t > 2022-06-13", "2022-06-13"),
+            ("x > 1-2", "1-2"),
+            ("x = y + 2-1", "2-1"),
+            ("x = 1st", "1st"),
+        ] {
+            match parse(s) {
+                Ok(rs) => panic!("unexpected result {:#?}", rs),
+                Err(e) => {
+                    assert_eq!(e.kind, ParseErrorKind::ExpectedField, "{}", s);
+                    assert_eq!(e.found, found, "{}", s);
+                }
+            }
+        }
+        assert!(parse("x > 1 - 2 && t > '2022-06-13' && a-b = c_1").is_ok());
+
+        match parse("updated_at > created_at && used <= quota") {
+            Ok(Expr::And { lhs, rhs, .. }) => {
+                match *lhs {
+                    Expr::Gt {
+                        field,
+                        value: Value::Field(other),
+                        ..
+                    } => assert_eq!(
+                        (field.as_str(), other.as_str()),
+                        ("updated_at", "created_at")
+                    ),
+                    rs => panic!("unexpected result {:#?}", rs),
+                }
+                assert!(matches!(
+                    *rhs,
+                    Expr::Lte {
+                        value: Value::Field(_),
+                        ..
+                    }
+                ));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+    }
+
+    #[test]
+    fn test_arith() {
+        use super::{ArithOp, Compare, Expr, Operand, ParseErrorKind, Value};
+
+        // * 优先级高于 +
+        match parse("a + b * 2 >= 100") {
+            Ok(Expr::Compare {
+                lhs: Operand::Arith { op, rhs, .. },
+                cmp: Compare::Gte,
+                rhs: Operand::Value(Value::Number(n)),
+                ..
+            }) => {
+                assert!(matches!(op, ArithOp::Add));
+                assert!(matches!(
+                    *rhs,
+                    Operand::Arith {
+                        op: ArithOp::Mul,
+                        ..
+                    }
+                ));
+                assert_eq!(n.as_i64(), Some(100));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 左结合: a - b - c => (a - b) - c
+        match parse("a - b - c = 0") {
+            Ok(Expr::Compare {
+                lhs:
+                    Operand::Arith {
+                        op: ArithOp::Sub,
+                        lhs,
+                        ..
+                    },
+                ..
+            }) => assert!(matches!(
+                *lhs,
+                Operand::Arith {
+                    op: ArithOp::Sub,
+                    ..
+                }
+            )),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        match parse("len(tags) + 1 > limit") {
+            Ok(Expr::Compare {
+                lhs: Operand::Arith { lhs, .. },
+                rhs: Operand::Field(limit),
+                ..
+            }) => {
+                assert!(matches!(*lhs, Operand::Len(_)));
+                assert_eq!(limit, "limit");
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 不含运算的比较保持原有的表达式
+        assert!(matches!(parse("a > 1"), Ok(Expr::Gt { .. })));
+        assert!(matches!(parse("(a) > (1)"), Ok(Expr::Gt { .. })));
+        assert!(matches!(parse("len(a) = 1"), Ok(Expr::Len { .. })));
+        assert!(matches!(parse("1 < a"), Ok(Expr::Compare { .. })));
+        // 减号两侧没有空格时属于字段名
+        assert!(matches!(parse("a-1 > 0"), Ok(Expr::Gt { field, .. }) if field == "a-1"));
+
+        match parse("a + 1 = 'x'") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::ExpectedField);
+                assert_eq!(e.found, "a + 1");
+            }
+        };
+    }
+
+    #[test]
+    fn test_function() {
+        use super::{Expr, Operand, ParseErrorKind, Value};
+        use crate::register_function;
+
+        match parse("lower(name) = 'bill'") {
+            Ok(Expr::Compare {
+                lhs: Operand::Call { name, args },
+                rhs: Operand::Value(Value::Text(v)),
+                ..
+            }) => {
+                assert_eq!(name, "lower");
+                assert!(matches!(args.as_slice(), [Operand::Field(f)] if f == "name"));
+                assert_eq!(v, "bill");
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // len 仍然解析为原有的表达式，同名字段不受影响
+        assert!(matches!(parse("LEN(tags) > 1"), Ok(Expr::Len { .. })));
+        assert!(matches!(parse("len = 1"), Ok(Expr::Eq { field, .. }) if field == "len"));
+        assert!(matches!(parse("t < now()"), Ok(Expr::Compare { .. })));
+
+        let cases = [
+            ("foo(a) = 1", ParseErrorKind::UnknownFunction),
+            ("lower(a, b) = 'x'", ParseErrorKind::InvalidArguments),
+            ("now(1) > t", ParseErrorKind::InvalidArguments),
+            ("exists('a') = true", ParseErrorKind::InvalidArguments),
+            (
+                "date_trunc('days', t) = @0",
+                ParseErrorKind::InvalidArguments,
+            ),
+            ("len(a + 1) > 1", ParseErrorKind::InvalidArguments),
+            ("lower(a) ! 'x'", ParseErrorKind::ExpectedField),
+        ];
+        for (sym, kind) in cases {
+            match parse(sym) {
+                Ok(rs) => panic!("unexpected result {:#?}", rs),
+                Err(e) => assert_eq!(e.kind, kind, "{}", sym),
+            }
+        }
+
+        assert!(!register_function("lower", 1, 1));
+        assert!(register_function("distance", 2, 4));
+        assert!(matches!(
+            parse("distance(x, y, 1) < 10"),
+            Ok(Expr::Compare { .. })
+        ));
+    }
+
+    #[test]
+    fn test_elem() {
+        use super::{Expr, Value};
+
+        match parse("any(lines, price > 10 && qty > 1) && tags has 'x'") {
+            Ok(Expr::And { lhs, rhs, .. }) => {
+                match *lhs {
+                    Expr::Any { field, expr, .. } => {
+                        assert_eq!(field, "lines");
+                        assert!(matches!(*expr, Expr::And { .. }));
+                    }
+                    rs => panic!("unexpected result {:#?}", rs),
+                }
+                match *rhs {
+                    Expr::Has {
+                        field,
+                        value: Value::List(items),
+                        ..
+                    } => {
+                        assert_eq!(field, "tags");
+                        assert_eq!(items.len(), 1);
+                    }
+                    rs => panic!("unexpected result {:#?}", rs),
+                }
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        assert!(matches!(
+            parse("ALL(lines, NOT qty = 0)"),
+            Ok(Expr::All { expr, .. }) if matches!(*expr, Expr::Not { .. })
+        ));
+        assert!(matches!(
+            parse("tags HAS ('a','b')"),
+            Ok(Expr::Has { value: Value::List(items), .. }) if items.len() == 2
+        ));
+        // 以关键字开头的字段名不受影响
+        assert!(matches!(
+            parse("anything = 1 && hash = 2"),
+            Ok(Expr::And { .. })
+        ));
+        assert!(parse("any(lines)").is_err());
+
+        // 关键字本身也可以作为字段名
+        for (s, expected) in [
+            ("all = 1", "all = 1"),
+            ("any > 1 && has = 2", "any > 1 && has = 2"),
+            (
+                "between = 1 || contains ! 'x'",
+                "between = 1 || contains ! 'x'",
+            ),
+            (
+                "startswith = endswith && ieq = 1",
+                "startswith = endswith && ieq = 1",
+            ),
+            ("any(all, has = 1)", "any(all, has = 1)"),
+            ("has has 'x'", "has has 'x'"),
+        ] {
+            match parse(s) {
+                Ok(expr) => assert_eq!(expr.to_string(), expected, "{}", s),
+                Err(e) => panic!("{}: {}", s, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_between() {
+        use super::{Bound, Expr, Value};
+
+        match parse("age between [18, 60)") {
+            Ok(Expr::Between {
+                field,
+                lower: Bound::Inclusive(Value::Number(lo)),
+                upper: Bound::Exclusive(Value::Number(hi)),
+                ..
+            }) => {
+                assert_eq!(field, "age");
+                assert_eq!((lo.as_i64(), hi.as_i64()), (Some(18), Some(60)));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        assert!(matches!(
+            parse("name BETWEEN ('a', 'b'] && t between [@0, @60]"),
+            Ok(Expr::And { lhs, rhs, .. })
+                if matches!(*lhs, Expr::Between { lower: Bound::Exclusive(_), upper: Bound::Inclusive(_), .. })
+                && matches!(*rhs, Expr::Between { .. })
+        ));
+        assert!(parse("a between (1)").is_err());
+        assert!(parse("a between [1, 2, 3]").is_err());
+    }
+
+    #[test]
+    fn test_match() {
+        use super::{Expr, MatchOp, ParseErrorKind, Value};
+
+        match parse("name contains 'a.(b' i") {
+            Ok(Expr::Match {
+                field,
+                op: MatchOp::Contains,
+                value: Value::Text(v),
+                flags,
+                ..
+            }) => {
+                assert_eq!(field, "name");
+                assert_eq!(v, "a.(b");
+                assert_eq!(flags, "i");
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 不带选项的 ! 保持原有的表达式
+        assert!(matches!(parse("name ! '^a'"), Ok(Expr::Like { .. })));
+        assert!(matches!(
+            parse("name ! '^a' m"),
+            Ok(Expr::Match {
+                op: MatchOp::Regex,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("name IEQ 'bill'"),
+            Ok(Expr::Match { op: MatchOp::IEq, flags, .. }) if flags == "i"
+        ));
+
+        match parse("name ! 'x' g") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::InvalidFlags);
+                assert_eq!(e.found, "g");
+            }
+        };
+        assert!(parse("name ieq 'x' i").is_err());
+        assert!(parse("name contains 1").is_err());
+    }
+
+    #[test]
+    fn test_keyword() {
+        use super::Expr;
+
+        let sql = "a = 1 AND (b IN (1, 2) OR c NOT IN ('x')) AND d IS NULL AND e IS NOT NULL \
+                   AND NOT f LIKE 'x' AND g NOT LIKE 'y' AND h not like 'z' i";
+        let sym = "a = 1 && (b ~ (1, 2) || c ~~ ('x')) && d ^ null && e ^^ null \
+                   && not f ! 'x' && g !! 'y' && h !! 'z' i";
+        assert_eq!(
+            parse(sql).unwrap().to_string(),
+            parse(sym).unwrap().to_string()
+        );
+
+        // 两种写法可以混用
+        assert_eq!(
+            parse("a = 1 and b ~ (1) || c is not null")
+                .unwrap()
+                .to_string(),
+            parse("a = 1 && b ~ (1) || c ^^ null").unwrap().to_string()
+        );
+
+        // 以关键字开头的字段名不受影响
+        assert!(matches!(
+            parse("order = 1 && index > 2 && in_stock = true && isbn ~ ('x') && notes = 'n'"),
+            Ok(Expr::And { .. })
+        ));
+        assert!(parse("a NOT = 1").is_err());
+
+        // 关键字的单词写法可以作为字段名
+        for (s, expected) in [
+            ("in = 1", "in = 1"),
+            ("is ^ null", "is ^ null"),
+            (
+                "not = 1 && and = 2 || or = 3",
+                "not = 1 && and = 2 || or = 3",
+            ),
+            ("like ! 'x' && a = like", "like ! 'x' && a = like"),
+            ("not in (1, 2)", "not ~ (1,2)"),
+            ("not (in = 1)", "!(in = 1)"),
+            ("a = 1 order by in desc", "a = 1"),
+        ] {
+            match crate::yacc_parse_query(s) {
+                Ok(query) => assert_eq!(query.expr.unwrap().to_string(), expected, "{}", s),
+                Err(e) => panic!("{}: {}", s, e),
+            }
+        }
+        // 符号写法不能作为字段名
+        for s in ["&& = 1", "~ = 1", "a = ^"] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_match_pattern() {
+        use super::MatchOp;
+
+        assert_eq!(MatchOp::Contains.pattern("a.(b)"), r"a\.\(b\)");
+        assert_eq!(MatchOp::StartsWith.pattern("$x"), r"\A\$x");
+        assert_eq!(MatchOp::EndsWith.pattern(".com"), r"\.com\z");
+        assert_eq!(MatchOp::IEq.pattern("a+b"), r"\Aa\+b\z");
+        assert_eq!(MatchOp::Regex.pattern("^a.b"), "^a.b");
+    }
 }
@@ -1,139 +1,317 @@
-%start Expr
-%token STRING NUMBER IDENT '>=' '<=' '>' '<' '<>' '!=' '(' ')' 'BOOL' 'LIKE' 'NLIKE' 'IN' 'NIN' 'NUMBER_ARRAY' 'STRING_ARRAY' 'IS' 'IS_NOT' 'NULL' 'LEN' 'BELONG' 'NO_BELONG'
-%left '||'
-%right '&&'
+%start Query
+%token '!' 'AND' 'OR' 'ORDER_BY' 'LIMIT' 'OFFSET' 'SELECT' 'QIDENT'
+%token STRING NUMBER IDENT '>=' '<=' '>' '<' '<>' '!=' '(' ')' 'BOOL' 'LIKE' 'NLIKE' 'IN' 'NIN' 'IS' 'IS_NOT' 'NULL' 'BELONG' 'NO_BELONG' 'NOT' 'PARAM' 'DATETIME' 'ANY' 'ALL' 'HAS' 'BETWEEN' '[' ']' 'CONTAINS' 'STARTSWITH' 'ENDSWITH' 'IEQ' ',' '+' '-' '*' '/' '%'
+%left '||' 'OR'
+%right '&&' 'AND'
+// not 后面紧跟 in、is、like、! 等时 not 作为字段名: not in (1, 2)，取反这类字段需要加括号
+%left 'IN' 'IS' 'LIKE' '!' 'HAS' 'BETWEEN' 'CONTAINS' 'STARTSWITH' 'ENDSWITH' 'IEQ'
+%right 'NOT'
 
 %%
-Expr -> Expr:
+// 条件之后依次为 order by、limit、offset、select 子句，均可省略
+// 子句按右递归展开，只在末尾归约空产生式，limit 等开头时与同名字段不冲突
+// span 记录第一个子句的位置，空产生式的 $span 不是空区间，只在子句中设置
+Query -> Result<Query, ParseError>:
+    Expr OrderPart { let mut query = $2?; query.expr = Some($1?); Ok(query) }
+  | OrderPart { $1 }
+  ;
+
+OrderPart -> Result<Query, ParseError>:
+    'ORDER_BY' Orders LimitPart { let mut query = $3?; query.sorts = $2?; query.span = $span; Ok(query) }
+  | LimitPart { $1 }
+  ;
+
+LimitPart -> Result<Query, ParseError>:
+    'LIMIT' 'NUMBER' OffsetPart { let mut query = $3?; let (s, span) = token_str($lexer, &$2)?; query.limit = Some(parse_count(s, span)?); query.span = $span; Ok(query) }
+  | OffsetPart { $1 }
+  ;
+
+OffsetPart -> Result<Query, ParseError>:
+    'OFFSET' 'NUMBER' SelectPart { let mut query = $3?; let (s, span) = token_str($lexer, &$2)?; query.offset = Some(parse_count(s, span)?); query.span = $span; Ok(query) }
+  | SelectPart { $1 }
+  ;
+
+SelectPart -> Result<Query, ParseError>:
+    'SELECT' Fields { Ok(Query { fields: $2?, span: $span, ..Default::default() }) }
+  | { Ok(Query::default()) }
+  ;
+
+Orders -> Result<Vec<OrderBy>, ParseError>:
+    Order { Ok(vec![$1?]) }
+  | Orders ',' Order { let mut orders = $1?; orders.push($3?); Ok(orders) }
+  ;
+
+Order -> Result<OrderBy, ParseError>:
+    Ident { Ok(OrderBy { field: $1?, desc: false }) }
+  | Ident 'IDENT' { let (s, span) = token_str($lexer, &$2)?; order_by($1?, s, span) }
+  ;
+
+// select 之后紧跟 in 等关键字时按条件解析，因此返回字段不能是运算关键字
+Fields -> Result<Vec<String>, ParseError>:
+    Name { Ok(vec![$1?]) }
+  | Fields ',' Name { let mut fields = $1?; fields.push($3?); Ok(fields) }
+  ;
+
+Expr -> Result<Expr, ParseError>:
     Factor { $1 }
   | Exprs  { $1 }
   ;
 
-Exprs -> Expr:
-    Factor '||' Factor { Expr::Or { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
-  | Factor '&&' Factor { Expr::And { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }  
-  | Exprs  '||' Factor { Expr::Or { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
-  | Exprs  '&&' Factor { Expr::And { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
-  | Factor '||' Exprs { Expr::Or { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
-  | Factor '&&' Exprs { Expr::And { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
-  | Exprs  '||' Exprs  { Expr::Or { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
-  | Exprs  '&&' Exprs  { Expr::And { span: $span, lhs: Box::new($1), rhs: Box::new($3) }  }
+Exprs -> Result<Expr, ParseError>:
+    Factor '||' Factor { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor '&&' Factor { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }  
+  | Exprs  '||' Factor { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  '&&' Factor { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor '||' Exprs { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor '&&' Exprs { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  '||' Exprs  { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  '&&' Exprs  { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor 'OR'  Factor { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor 'AND' Factor { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  'OR'  Factor { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  'AND' Factor { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor 'OR'  Exprs  { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Factor 'AND' Exprs  { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  'OR'  Exprs  { Ok(Expr::Or { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
+  | Exprs  'AND' Exprs  { Ok(Expr::And { span: $span, lhs: Box::new($1?), rhs: Box::new($3?) }) }
   | '(' Exprs ')' { $2 }
   ;
 
-Factor -> Expr:
+Factor -> Result<Expr, ParseError>:
     '(' Factor ')'  { $2 }
+  | NotExpr { $1 }
   | TextCompare   { $1 }
-  | NumberCompare { $1 }
-  | BelongCompare { $1 }
+  | ArithCompare { $1 }
+  | ListCompare { $1 }
   | BoolExpr { $1 }
   | IsExpr { $1 }
-  | LenExpr { $1 }
-  ;
-
-LenExpr -> Expr:
-    'LEN''('Ident')' '='  Number   { Expr::Len { span: $span, field: $3, cmp: Compare::Eq, value: Value::Number($6) } }
-  | 'LEN''('Ident')' '>=' Number   { Expr::Len { span: $span, field: $3, cmp: Compare::Gte, value: Value::Number($6) } }
-  | 'LEN''('Ident')' '<=' Number   { Expr::Len { span: $span, field: $3, cmp: Compare::Lte, value: Value::Number($6) } }
-  | 'LEN''('Ident')' '>'  Number   { Expr::Len { span: $span, field: $3, cmp: Compare::Gt, value: Value::Number($6) } }
-  | 'LEN''('Ident')' '<'  Number   { Expr::Len { span: $span, field: $3, cmp: Compare::Lt, value: Value::Number($6) } }
-  | 'LEN''('Ident')' '!='  Number  { Expr::Len { span: $span, field: $3, cmp: Compare::Ne, value: Value::Number($6) } }
-  | 'LEN''('Ident')' '<>'  Number  { Expr::Len { span: $span, field: $3, cmp: Compare::Ne, value: Value::Number($6) } }
-  ;
-
-IsExpr -> Expr:
-    Ident 'IS' Null     { Expr::IsNull    { span: $span, field: $1 } }
-  | Ident 'IS_NOT' Null { Expr::IsNotNull { span: $span, field: $1 } }
-  ;
-
-BoolExpr -> Expr:
-    Ident '='  Bool { Expr::Eq { span: $span, field: $1, value: Value::Bool($3) } }
-  | Ident '<>' Bool { Expr::Ne { span: $span, field: $1, value: Value::Bool($3) } }
-  | Ident '!=' Bool { Expr::Ne { span: $span, field: $1, value: Value::Bool($3) } }
-  ;
-
-TextCompare -> Expr:
-    Ident '='  Text { Expr::Eq { span: $span, field: $1, value: Value::Text($3) } } 
-  | Ident '>'  Text { Expr::Gt { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident '<'  Text { Expr::Lt { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident '>=' Text { Expr::Gte { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident '<=' Text { Expr::Lte { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident '<>' Text { Expr::Ne { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident '!=' Text { Expr::Ne { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident 'LIKE'  Text  { Expr::Like { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident 'NLIKE' Text  { Expr::NotLike { span: $span, field: $1, value: Value::Text($3) } }
-  | Ident 'IN'  TextArray { Expr::In { span: $span, field: $1, value: $3 } }
-  | Ident 'NIN' TextArray { Expr::NotIn { span: $span, field: $1, value: $3 } }
-  ;
-
-NumberCompare -> Expr:
-    Ident '='  Number { Expr::Eq { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident '>'  Number { Expr::Gt { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident '<'  Number { Expr::Lt { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident '>=' Number { Expr::Gte { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident '<=' Number { Expr::Lte { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident '<>' Number { Expr::Ne { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident '!=' Number { Expr::Ne { span: $span, field: $1, value: Value::Number($3) } }
-  | Ident 'IN'  IntArray { Expr::In    { span: $span, field: $1, value: $3 } }
-  | Ident 'NIN' IntArray { Expr::NotIn { span: $span, field: $1, value: $3 } }
-  ;
-
-BelongCompare -> Expr:
-    Ident 'BELONG'  TextArray { Expr::Belong { span: $span, field: $1, value: $3 } }
-  | Ident 'BELONG'  IntArray  { Expr::Belong { span: $span, field: $1, value: $3 } }
-  | Ident 'NO_BELONG'  TextArray { Expr::NoBelong { span: $span, field: $1, value: $3 } }
-  | Ident 'NO_BELONG'  IntArray  { Expr::NoBelong { span: $span, field: $1, value: $3 } }
-  ;
-
-Text -> String:
-  'STRING' { remove_apostrophe($lexer.span_str($1.as_ref().unwrap().span()).to_string()) } 
-  ;
-Ident -> String:
-  'IDENT' { remove_apostrophe($lexer.span_str($1.as_ref().unwrap().span()).to_string()) } 
-  ;
-Number -> Number:
-  'NUMBER' { $lexer.span_str($1.as_ref().unwrap().span()).parse::<Number>().unwrap() }
-  ;
-Bool -> bool:
-  'BOOL' { $lexer.span_str($1.as_ref().unwrap().span()).parse::<bool>().unwrap() }
-  ;
-IntArray -> Value:
-  'NUMBER_ARRAY' 
-  {
-       let mut rs = vec![];
-       let src = String::from($lexer.span_str($1.as_ref().unwrap().span()));
-        let binding = src
-            .trim_start_matches("(")
-            .to_string();
-        let items = binding
-            .trim_end_matches(")")
-            .split(",");
-      for item in items {
-          rs.push(Value::Number(item.parse::<Number>().unwrap()));
-      }
-      Value::List(rs)
-  }
-  ;
-TextArray -> Value:
-  'STRING_ARRAY' 
-  {
-       let mut rs = vec![];
-       let src = String::from($lexer.span_str($1.as_ref().unwrap().span()));
-        let binding = src
-            .trim_start_matches("(")
-            .to_string();
-        let items = binding
-            .trim_end_matches(")")
-            .split(",");
-      for item in items {
-          rs.push(Value::Text(remove_apostrophe(item.parse::<String>().unwrap())));
-      }
-      Value::List(rs)
-  }
-  ;
-
-Null -> Value:
-  'NULL' { Value::Null }
+  | ParamCompare { $1 }
+  | DateCompare { $1 }
+  | ElemExpr { $1 }
+  | BetweenExpr { $1 }
+  | MatchExpr { $1 }
+  ;
+
+NotExpr -> Result<Expr, ParseError>:
+    '!' '(' Factor ')' { Ok(Expr::Not { span: $span, expr: Box::new($3?) }) }
+  | '!' '(' Exprs ')'  { Ok(Expr::Not { span: $span, expr: Box::new($3?) }) }
+  | 'NOT' Factor          { Ok(Expr::Not { span: $span, expr: Box::new($2?) }) }
+  | 'NOT' '(' Exprs ')'   { Ok(Expr::Not { span: $span, expr: Box::new($3?) }) }
+  ;
+
+IsExpr -> Result<Expr, ParseError>:
+    Arith 'IS' Null     { Ok(Expr::IsNull    { span: $span, field: field_of($span, $1?)? }) }
+  | Arith 'IS_NOT' Null { Ok(Expr::IsNotNull { span: $span, field: field_of($span, $1?)? }) }
+  | Arith 'IS' 'NOT' Null { Ok(Expr::IsNotNull { span: $span, field: field_of($span, $1?)? }) }
+  ;
+
+BoolExpr -> Result<Expr, ParseError>:
+    Arith '=' Bool { compare_value($span, $1?, Compare::Eq, Value::Bool($3?)) }
+  | Arith '<>' Bool { compare_value($span, $1?, Compare::Ne, Value::Bool($3?)) }
+  | Arith '!=' Bool { compare_value($span, $1?, Compare::Ne, Value::Bool($3?)) }
+  ;
+
+TextCompare -> Result<Expr, ParseError>:
+    Arith '=' Text { compare_value($span, $1?, Compare::Eq, Value::Text($3?)) }
+  | Arith '>' Text { compare_value($span, $1?, Compare::Gt, Value::Text($3?)) }
+  | Arith '<' Text { compare_value($span, $1?, Compare::Lt, Value::Text($3?)) }
+  | Arith '>=' Text { compare_value($span, $1?, Compare::Gte, Value::Text($3?)) }
+  | Arith '<=' Text { compare_value($span, $1?, Compare::Lte, Value::Text($3?)) }
+  | Arith '<>' Text { compare_value($span, $1?, Compare::Ne, Value::Text($3?)) }
+  | Arith '!=' Text { compare_value($span, $1?, Compare::Ne, Value::Text($3?)) }
+  | Arith Like Text  { Ok(Expr::Like { span: $span, field: field_of($span, $1?)?, value: Value::Text($3?) }) }
+  | Arith 'NLIKE' Text  { Ok(Expr::NotLike { span: $span, field: field_of($span, $1?)?, value: Value::Text($3?) }) }
+  | Arith 'NOT' 'LIKE' Text { Ok(Expr::NotLike { span: $span, field: field_of($span, $1?)?, value: Value::Text($4?) }) }
+  ;
+
+// 数字、字段、参数和函数调用之间的比较，两侧都可以是算术表达式
+ArithCompare -> Result<Expr, ParseError>:
+    Arith '='  Arith { compare($span, $1?, Compare::Eq, $3?) }
+  | Arith '>'  Arith { compare($span, $1?, Compare::Gt, $3?) }
+  | Arith '<'  Arith { compare($span, $1?, Compare::Lt, $3?) }
+  | Arith '>=' Arith { compare($span, $1?, Compare::Gte, $3?) }
+  | Arith '<=' Arith { compare($span, $1?, Compare::Lte, $3?) }
+  | Arith '<>' Arith { compare($span, $1?, Compare::Ne, $3?) }
+  | Arith '!=' Arith { compare($span, $1?, Compare::Ne, $3?) }
+  ;
+
+// * / % 优先级高于 + -，均为左结合
+Arith -> Result<Operand, ParseError>:
+    Term { $1 }
+  | Arith '+' Term { Ok(Operand::arith(ArithOp::Add, $1?, $3?)) }
+  | Arith '-' Term { Ok(Operand::arith(ArithOp::Sub, $1?, $3?)) }
+  ;
+
+Term -> Result<Operand, ParseError>:
+    Atom { $1 }
+  | Term '*' Atom { Ok(Operand::arith(ArithOp::Mul, $1?, $3?)) }
+  | Term '/' Atom { Ok(Operand::arith(ArithOp::Div, $1?, $3?)) }
+  | Term '%' Atom { Ok(Operand::arith(ArithOp::Rem, $1?, $3?)) }
+  ;
+
+Atom -> Result<Operand, ParseError>:
+    Ident  { Ok(Operand::Field($1?)) }
+  | Number { Ok(Operand::Value(Value::Number($1?))) }
+  | Param  { Ok(Operand::Value($1?)) }
+  | Name '(' ')' { call($span, $1?, vec![]) }
+  | Name '(' Args ')' { call($span, $1?, $3?) }
+  | '(' Arith ')' { $2 }
+  ;
+
+Args -> Result<Vec<Operand>, ParseError>:
+    Arg { Ok(vec![$1?]) }
+  | Args ',' Arg { let mut args = $1?; args.push($3?); Ok(args) }
+  ;
+
+Arg -> Result<Operand, ParseError>:
+    Arith    { $1 }
+  | Text     { Ok(Operand::Value(Value::Text($1?))) }
+  | DateTime { Ok(Operand::Value($1?)) }
+  ;
+
+ElemExpr -> Result<Expr, ParseError>:
+    'ANY' '(' Ident ',' Factor ')' { Ok(Expr::Any { span: $span, field: $3?, expr: Box::new($5?) }) }
+  | 'ANY' '(' Ident ',' Exprs ')'  { Ok(Expr::Any { span: $span, field: $3?, expr: Box::new($5?) }) }
+  | 'ALL' '(' Ident ',' Factor ')' { Ok(Expr::All { span: $span, field: $3?, expr: Box::new($5?) }) }
+  | 'ALL' '(' Ident ',' Exprs ')'  { Ok(Expr::All { span: $span, field: $3?, expr: Box::new($5?) }) }
+  | Arith 'HAS' Item  { Ok(Expr::Has { span: $span, field: field_of($span, $1?)?, value: Value::List(vec![$3?]) }) }
+  | Arith 'HAS' List  { Ok(Expr::Has { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'HAS' Param { Ok(Expr::Has { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  ;
+
+MatchExpr -> Result<Expr, ParseError>:
+    Arith Like Pattern Flags  { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::Regex, value: $3?, flags: $4? }) }
+  | Arith 'NLIKE' Pattern Flags { Ok(Expr::NotMatch { span: $span, field: field_of($span, $1?)?, value: $3?, flags: $4? }) }
+  | Arith 'NOT' 'LIKE' Pattern Flags { Ok(Expr::NotMatch { span: $span, field: field_of($span, $1?)?, value: $4?, flags: $5? }) }
+  | Arith 'CONTAINS' Pattern        { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::Contains, value: $3?, flags: String::new() }) }
+  | Arith 'CONTAINS' Pattern Flags  { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::Contains, value: $3?, flags: $4? }) }
+  | Arith 'STARTSWITH' Pattern       { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::StartsWith, value: $3?, flags: String::new() }) }
+  | Arith 'STARTSWITH' Pattern Flags { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::StartsWith, value: $3?, flags: $4? }) }
+  | Arith 'ENDSWITH' Pattern       { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::EndsWith, value: $3?, flags: String::new() }) }
+  | Arith 'ENDSWITH' Pattern Flags { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::EndsWith, value: $3?, flags: $4? }) }
+  | Arith 'IEQ' Pattern { Ok(Expr::Match { span: $span, field: field_of($span, $1?)?, op: MatchOp::IEq, value: $3?, flags: "i".to_string() }) }
+  ;
+
+Pattern -> Result<Value, ParseError>:
+    Text  { Ok(Value::Text($1?)) }
+  | Param { $1 }
+  ;
+
+Flags -> Result<String, ParseError>:
+  'IDENT' { let (s, span) = token_str($lexer, &$1)?; parse_flags(s, span) }
+  ;
+
+BetweenExpr -> Result<Expr, ParseError>:
+    Arith 'BETWEEN' '[' RangeValue ',' RangeValue ']' { Ok(Expr::Between { span: $span, field: field_of($span, $1?)?, lower: Bound::Inclusive($4?), upper: Bound::Inclusive($6?) }) }
+  | Arith 'BETWEEN' '[' RangeValue ',' RangeValue ')' { Ok(Expr::Between { span: $span, field: field_of($span, $1?)?, lower: Bound::Inclusive($4?), upper: Bound::Exclusive($6?) }) }
+  | Arith 'BETWEEN' '(' RangeValue ',' RangeValue ']' { Ok(Expr::Between { span: $span, field: field_of($span, $1?)?, lower: Bound::Exclusive($4?), upper: Bound::Inclusive($6?) }) }
+  | Arith 'BETWEEN' '(' RangeValue ',' RangeValue ')' { Ok(Expr::Between { span: $span, field: field_of($span, $1?)?, lower: Bound::Exclusive($4?), upper: Bound::Exclusive($6?) }) }
+  ;
+
+RangeValue -> Result<Value, ParseError>:
+    Text     { Ok(Value::Text($1?)) }
+  | Number   { Ok(Value::Number($1?)) }
+  | DateTime { $1 }
+  | Param    { $1 }
+  ;
+
+ListCompare -> Result<Expr, ParseError>:
+    Arith 'IN'  List { Ok(Expr::In { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NIN' List { Ok(Expr::NotIn { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NOT' 'IN' List { Ok(Expr::NotIn { span: $span, field: field_of($span, $1?)?, value: $4? }) }
+  | Arith 'BELONG'  List { Ok(Expr::Belong { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NO_BELONG'  List { Ok(Expr::NoBelong { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  ;
+
+ParamCompare -> Result<Expr, ParseError>:
+    Arith Like Param { Ok(Expr::Like { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NLIKE' Param { Ok(Expr::NotLike { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'IN'  Param { Ok(Expr::In { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NIN' Param { Ok(Expr::NotIn { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NOT' 'IN' Param { Ok(Expr::NotIn { span: $span, field: field_of($span, $1?)?, value: $4? }) }
+  | Arith 'NOT' 'LIKE' Param { Ok(Expr::NotLike { span: $span, field: field_of($span, $1?)?, value: $4? }) }
+  | Arith 'BELONG'  Param { Ok(Expr::Belong { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  | Arith 'NO_BELONG'  Param { Ok(Expr::NoBelong { span: $span, field: field_of($span, $1?)?, value: $3? }) }
+  ;
+
+DateCompare -> Result<Expr, ParseError>:
+    Arith '=' DateTime { compare_value($span, $1?, Compare::Eq, $3?) }
+  | Arith '>' DateTime { compare_value($span, $1?, Compare::Gt, $3?) }
+  | Arith '<' DateTime { compare_value($span, $1?, Compare::Lt, $3?) }
+  | Arith '>=' DateTime { compare_value($span, $1?, Compare::Gte, $3?) }
+  | Arith '<=' DateTime { compare_value($span, $1?, Compare::Lte, $3?) }
+  | Arith '<>' DateTime { compare_value($span, $1?, Compare::Ne, $3?) }
+  | Arith '!=' DateTime { compare_value($span, $1?, Compare::Ne, $3?) }
+  ;
+
+// ! 只在括号前表示取反，like 只表示匹配
+Like -> Result<(), ParseError>:
+    'LIKE' { Ok(()) }
+  | '!'    { Ok(()) }
+  ;
+
+Text -> Result<String, ParseError>:
+  'STRING' { let (s, span) = token_str($lexer, &$1)?; unescape(s, span) }
+  ;
+// 子句关键字在条件中仍可作为字段名和函数名，反引号中的字段名可以包含任意字符: `a b`
+Name -> Result<String, ParseError>:
+    'IDENT'  { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'QIDENT' { let (s, span) = token_str($lexer, &$1)?; quoted_field(s, span) }
+  | 'LIMIT'  { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'OFFSET' { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'SELECT' { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  ;
+// 运算关键字的单词写法可以作为字段名，not(...)、any(...) 等仍按关键字解析
+Ident -> Result<String, ParseError>:
+    Name   { $1 }
+  | 'NOT'  { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'AND'  { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'OR'   { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'IN'   { let (s, span) = token_str($lexer, &$1)?; keyword_field(s, span) }
+  | 'LIKE' { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'IS'   { let (s, span) = token_str($lexer, &$1)?; keyword_field(s, span) }
+  | 'ANY'        { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'ALL'        { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'HAS'        { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'BETWEEN'    { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'CONTAINS'   { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'STARTSWITH' { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'ENDSWITH'   { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  | 'IEQ'        { Ok(token_str($lexer, &$1)?.0.to_string()) }
+  ;
+Number -> Result<Number, ParseError>:
+  'NUMBER' { let (s, span) = token_str($lexer, &$1)?; parse_number(s, span) }
+  ;
+Bool -> Result<bool, ParseError>:
+  'BOOL' { let (s, span) = token_str($lexer, &$1)?; parse_bool(s, span) }
+  ;
+
+DateTime -> Result<Value, ParseError>:
+  'DATETIME' { let (s, span) = token_str($lexer, &$1)?; parse_datetime(s, span) }
+  ;
+
+List -> Result<Value, ParseError>:
+    '(' Items ')' { Ok(Value::List($2?)) }
+  | '(' ')'       { Ok(Value::List(vec![])) }
+  ;
+Items -> Result<Vec<Value>, ParseError>:
+    Item { Ok(vec![$1?]) }
+  | Items ',' Item { let mut items = $1?; items.push($3?); Ok(items) }
+  ;
+Item -> Result<Value, ParseError>:
+    Text     { Ok(Value::Text($1?)) }
+  | Number   { Ok(Value::Number($1?)) }
+  | DateTime { $1 }
+  | Bool     { Ok(Value::Bool($1?)) }
+  | Null     { $1 }
+  ;
+
+Param -> Result<Value, ParseError>:
+  'PARAM' { Ok(Value::Param(token_str($lexer, &$1)?.0.trim_start_matches(':').to_string())) }
+  ;
+
+Null -> Result<Value, ParseError>:
+  'NULL' { Ok(Value::Null) }
   ;
 
 %%
@@ -0,0 +1,487 @@
+use std::fmt;
+
+use chrono::SecondsFormat;
+
+use crate::{ArithOp, Compare, Expr, MatchOp, Operand, OrderBy, Query, Value};
+
+// 输出可被 yacc_parse 重新解析为同一棵语法树的条件字符串
+// && 优先级高于 ||，|| 左结合，&& 右结合，只在需要时加括号
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Or { lhs, rhs, .. } => {
+                write_operand(f, lhs, false, true)?;
+                write!(f, " || ")?;
+                write_operand(f, rhs, false, false)
+            }
+            Expr::And { lhs, rhs, .. } => {
+                write_operand(f, lhs, true, true)?;
+                write!(f, " && ")?;
+                write_operand(f, rhs, true, false)
+            }
+            Expr::Not { expr, .. } => write!(f, "!({})", expr),
+            Expr::Eq { field, value, .. } => write!(f, "{} = {}", Name::new(field), value),
+            Expr::Ne { field, value, .. } => write!(f, "{} != {}", Name::new(field), value),
+            Expr::Gt { field, value, .. } => write!(f, "{} > {}", Name::new(field), value),
+            Expr::Gte { field, value, .. } => write!(f, "{} >= {}", Name::new(field), value),
+            Expr::Lt { field, value, .. } => write!(f, "{} < {}", Name::new(field), value),
+            Expr::Lte { field, value, .. } => write!(f, "{} <= {}", Name::new(field), value),
+            Expr::Like { field, value, .. } => write!(f, "{} ! {}", Name::new(field), value),
+            Expr::NotLike { field, value, .. } => write!(f, "{} !! {}", Name::new(field), value),
+            Expr::In { field, value, .. } => write!(f, "{} ~ {}", Name::new(field), value),
+            Expr::NotIn { field, value, .. } => write!(f, "{} ~~ {}", Name::new(field), value),
+            Expr::IsNull { field, .. } => write!(f, "{} ^ null", Name::new(field)),
+            Expr::IsNotNull { field, .. } => write!(f, "{} ^^ null", Name::new(field)),
+            Expr::Len {
+                field, cmp, value, ..
+            } => write!(f, "len({}) {} {}", Name::new(field), cmp, value),
+            Expr::Belong { field, value, .. } => write!(f, "{} << {}", Name::new(field), value),
+            Expr::NoBelong { field, value, .. } => write!(f, "{} >> {}", Name::new(field), value),
+            Expr::Compare { lhs, cmp, rhs, .. } => write!(f, "{} {} {}", lhs, cmp, rhs),
+            Expr::Any { field, expr, .. } => write!(f, "any({}, {})", Name::new(field), expr),
+            Expr::All { field, expr, .. } => write!(f, "all({}, {})", Name::new(field), expr),
+            Expr::Has {
+                field,
+                value: Value::List(items),
+                ..
+            } if items.len() == 1 => write!(f, "{} has {}", Name::new(field), items[0]),
+            Expr::Has { field, value, .. } => write!(f, "{} has {}", Name::new(field), value),
+            Expr::Match {
+                field,
+                op: MatchOp::IEq,
+                value,
+                ..
+            } => write!(f, "{} ieq {}", Name::new(field), value),
+            Expr::Match {
+                field,
+                op,
+                value,
+                flags,
+                ..
+            } => {
+                write!(f, "{} {} {}", Name::new(field), op, value)?;
+                if !flags.is_empty() {
+                    write!(f, " {}", flags)?;
+                }
+                Ok(())
+            }
+            Expr::NotMatch {
+                field,
+                value,
+                flags,
+                ..
+            } => {
+                write!(f, "{} !! {}", Name::new(field), value)?;
+                if !flags.is_empty() {
+                    write!(f, " {}", flags)?;
+                }
+                Ok(())
+            }
+            Expr::Between {
+                field,
+                lower,
+                upper,
+                ..
+            } => write!(
+                f,
+                "{} between {}{}, {}{}",
+                Name::new(field),
+                if lower.is_inclusive() { "[" } else { "(" },
+                lower.value(),
+                upper.value(),
+                if upper.is_inclusive() { "]" } else { ")" },
+            ),
+        }
+    }
+}
+
+// 逻辑运算的操作数，优先级较低或结合方向相反时加括号
+// a || (b || c)、(a && b) && c、(a || b) && c
+fn write_operand(
+    f: &mut fmt::Formatter<'_>,
+    expr: &Expr,
+    in_and: bool,
+    is_lhs: bool,
+) -> fmt::Result {
+    let low = match expr {
+        Expr::Or { .. } => in_and || !is_lhs,
+        Expr::And { .. } => in_and && is_lhs,
+        _ => false,
+    };
+    if low {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+// 输出可被 yacc_parse_query 重新解析的查询，省略的子句不输出
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(expr) = &self.expr {
+            parts.push(expr.to_string());
+        }
+        if !self.sorts.is_empty() {
+            let sorts: Vec<String> = self.sorts.iter().map(|s| s.to_string()).collect();
+            parts.push(format!("order by {}", sorts.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            parts.push(format!("limit {}", limit));
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("offset {}", offset));
+        }
+        if !self.fields.is_empty() {
+            let fields: Vec<String> = self
+                .fields
+                .iter()
+                .map(|s| Name::select(s).to_string())
+                .collect();
+            parts.push(format!("select {}", fields.join(",")));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.desc {
+            true => write!(f, "{} desc", Name::new(&self.field)),
+            false => write!(f, "{} asc", Name::new(&self.field)),
+        }
+    }
+}
+
+impl fmt::Display for Compare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Compare::Eq => "=",
+            Compare::Ne => "!=",
+            Compare::Gt => ">",
+            Compare::Gte => ">=",
+            Compare::Lt => "<",
+            Compare::Lte => "<=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for MatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            MatchOp::Regex => "!",
+            MatchOp::Contains => "contains",
+            MatchOp::StartsWith => "startswith",
+            MatchOp::EndsWith => "endswith",
+            MatchOp::IEq => "ieq",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for ArithOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            ArithOp::Add => "+",
+            ArithOp::Sub => "-",
+            ArithOp::Mul => "*",
+            ArithOp::Div => "/",
+            ArithOp::Rem => "%",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl ArithOp {
+    fn precedence(&self) -> u8 {
+        match self {
+            ArithOp::Add | ArithOp::Sub => 1,
+            ArithOp::Mul | ArithOp::Div | ArithOp::Rem => 2,
+        }
+    }
+}
+
+// 运算符两侧保留空格，a-1 会被当作字段名
+// 左结合: 右侧优先级不高于当前运算时加括号，a - (b - c)、a * (b + c)
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Field(name) => write!(f, "{}", Name::new(name)),
+            Operand::Value(value) => write!(f, "{}", value),
+            Operand::Len(field) => write!(f, "len({})", Name::new(field)),
+            Operand::Call { name, args } => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Operand::Arith { op, lhs, rhs } => {
+                let level = |o: &Operand| match o {
+                    Operand::Arith { op, .. } => op.precedence(),
+                    _ => u8::MAX,
+                };
+                if level(lhs) < op.precedence() {
+                    write!(f, "({})", lhs)?;
+                } else {
+                    write!(f, "{}", lhs)?;
+                }
+                write!(f, " {} ", op)?;
+                if level(rhs) <= op.precedence() {
+                    write!(f, "({})", rhs)
+                } else {
+                    write!(f, "{}", rhs)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Text(s) => write_quoted(f, s, '\''),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::DateTime(dt) => write!(f, "{}", dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            Value::List(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+            Value::Len(field) => write!(f, "len({})", Name::new(field)),
+            Value::Param(name) => write!(f, ":{}", name),
+            Value::Field(name) => write!(f, "{}", Name::new(name)),
+            Value::Null => write!(f, "null"),
+        }
+    }
+}
+
+// 与字面量同名的字段名需要加反引号，运算关键字在条件中可以作为字段名
+const LITERALS: [&str; 3] = ["null", "true", "false"];
+// select 之后紧跟运算关键字时按条件解析，返回字段与之同名时需要加反引号
+const KEYWORDS: [&str; 15] = [
+    "not",
+    "and",
+    "or",
+    "in",
+    "like",
+    "is",
+    "any",
+    "all",
+    "has",
+    "between",
+    "contains",
+    "startswith",
+    "endswith",
+    "ieq",
+    "order",
+];
+
+// 字段名不是普通标识符或与关键字同名时加反引号，保证重新解析为同一字段: `a b`
+struct Name<'a>(&'a str, &'a [&'a str]);
+
+impl<'a> Name<'a> {
+    fn new(s: &'a str) -> Self {
+        Name(s, &LITERALS)
+    }
+
+    fn select(s: &'a str) -> Self {
+        Name(s, &KEYWORDS)
+    }
+}
+
+impl fmt::Display for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.0;
+        let plain = s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !LITERALS
+                .iter()
+                .chain(self.1)
+                .any(|k| k.eq_ignore_ascii_case(s));
+        match plain {
+            true => write!(f, "{}", s),
+            false => write_quoted(f, s, '`'),
+        }
+    }
+}
+
+// 字符串使用单引号，字段名使用反引号，转义引号、反斜杠和控制字符
+fn write_quoted(f: &mut fmt::Formatter<'_>, s: &str, quote: char) -> fmt::Result {
+    write!(f, "{}", quote)?;
+    for c in s.chars() {
+        match c {
+            c if c == quote => write!(f, "\\{}", c)?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "{}", quote)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{yacc_parse as parse, Expr};
+
+    fn print(s: &str) -> String {
+        match parse(s) {
+            Ok(expr) => expr.to_string(),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let syms = [
+            "a = 1",
+            "a != 'x'",
+            "a = true && b != false",
+            "a > 1.5 || b <= 'abc'",
+            "name ! '^a.' && name !! \"it's\"",
+            "a ~ (1,2,3) && b ~~ ('x','y')",
+            "c ~ (\"it's\",\"x\")",
+            "a = 'it\\'s \\\\ \\u00e9' || b ~ ('x, y', \"z\")",
+            "tags << ('a','b') || tags >> (1,2)",
+            "a ^ null && b ^^ null",
+            "len(name) >= 3 && len(ids) != 0",
+            "name = :name && id ~ :ids",
+            "updated_at > created_at && used != quota",
+            "t >= 2022-06-13T16:00:00+08:00 && t < 2022-06-14T00:00:00.5Z",
+            "t ~ (2022-06-13T16:00:00Z,2022-06-14T00:00:00-05:00)",
+            "!(a = 1 || b = 2) && c = 3",
+            "price * qty >= 100 && len(tags) + 1 > limit",
+            "a - (b - c) = 0 || (a + b) * c % 7 != d / 2",
+            "1 < a - -1 && a * :k <= 10",
+            "lower(name) = 'bill' && exists(a) = true && type(a) != 'string'",
+            "date_trunc('day', t) = 2022-06-13T00:00:00Z || t < now()",
+            "abs(a - b) * 2 < size(tags) + 1",
+            "any(lines, price > 10 && qty > 1) || all(lines, qty > 0)",
+            "tags has 'x' && tags has ('x','y') && ids has :ids",
+            "any(orders, any(lines, sku = 'a'))",
+            "a between [1, 10) && b between ('a', 'b']",
+            "name ! '^b.l' im && name !! 'x' i",
+            "mail endswith '.com' i && name contains 'a.(b' && name startswith :p && name ieq 'Bill'",
+            "t between (2022-06-13T00:00:00Z, 2022-06-14T00:00:00Z) || c between [:lo, :hi]",
+            "a = 1 || b = 2 || c = 3",
+            "a = 1 && b = 2 && c = 3",
+            "a = 1 || b = 2 && c = 3",
+            "(a = 1 || b = 2) && c = 3",
+            "(a = 1 && b = 2) && c = 3",
+            "a = 1 || (b = 2 || c = 3)",
+        ];
+        for sym in syms {
+            let printed = print(sym);
+            // 输出的字符串重新解析后再输出保持不变
+            assert_eq!(print(&printed), printed, "{}", sym);
+        }
+
+        assert_eq!(print("a=1&&b='x'"), "a = 1 && b = 'x'");
+        assert_eq!(print("a=1&&(b='x'||c<>2)"), "a = 1 && (b = 'x' || c != 2)");
+        assert_eq!(print("a <> 1"), "a != 1");
+        assert_eq!(print("t = @0"), "t = 1970-01-01T00:00:00Z");
+        assert_eq!(print("NOT a = 1"), "!(a = 1)");
+        assert_eq!(print("c ~ ( 'x' , 'y' )"), "c ~ ('x','y')");
+        assert_eq!(print(r#"a = "it's""#), r#"a = 'it\'s'"#);
+        assert_eq!(print(r#"a = 'say "hi"\n'"#), r#"a = 'say "hi"\n'"#);
+        assert_eq!(print("c ~ ('a,b','c')"), "c ~ ('a,b','c')");
+        assert_eq!(print("a = -1.5e3 || b = -2"), "a = -1500.0 || b = -2");
+        // 未知转义保留反斜杠，输出时转义反斜杠本身
+        assert_eq!(print(r"a ! '^\d+\.x'"), r"a ! '^\\d+\\.x'");
+        assert_eq!(print(r"a ! '^\\d+\\.x'"), r"a ! '^\\d+\\.x'");
+        assert_eq!(print("(a=1 || b=2) && c=3"), "(a = 1 || b = 2) && c = 3");
+        assert_eq!(print("a+b*c>=(d)"), "a + b * c >= d");
+        assert_eq!(print("(a*b)+((c - d) - e)>0"), "a * b + (c - d - e) > 0");
+        assert_eq!(print("a ! 'x' mii"), "a ! 'x' im");
+        assert_eq!(print("a !! 'x' m"), "a !! 'x' m");
+        assert_eq!(print("a not like 'x' m"), "a !! 'x' m");
+        assert_eq!(print("a=1 || (b=2 && c=3)"), "a = 1 || b = 2 && c = 3");
+    }
+
+    #[test]
+    fn test_round_trip_builder() {
+        use crate::{field, yacc_parse_query, Bound, OrderBy, Query, Value};
+
+        // 代码中构造的表达式: 空列表、bool 和 null 列表、需要加反引号的字段名
+        let exprs = [
+            field("a").is_in(Vec::<i32>::new()),
+            field("a").not_in(Vec::<String>::new()),
+            field("tags").has(Vec::<i32>::new()),
+            field("flag").is_in([Value::Bool(true), Value::Null]),
+            field("a b").eq(1).and(field("it's `x`").contains("y")),
+            field("null").is_null().or(field("true").ne(false)),
+            field("2fa").eq(true).and(field("-a").gt(1)),
+            field("in").is_in([1]).and(field("not").lt(2)),
+            field("名字").ieq("x").and(field("a\nb").len().gt(1)),
+            field("items list").any(field("sku").eq("a")),
+            field("a.b c").between(
+                Bound::Inclusive(Value::from(1)),
+                Bound::Exclusive(Value::from(2)),
+            ),
+        ];
+        for expr in exprs {
+            let printed = expr.to_string();
+            match parse(&printed) {
+                Ok(parsed) => assert_eq!(parsed.to_json(false), expr.to_json(false), "{}", printed),
+                Err(e) => panic!("{}: {}", printed, e),
+            }
+        }
+
+        let query = Query {
+            expr: Some(field("in").eq(1)),
+            sorts: vec![OrderBy {
+                field: "order by".to_string(),
+                desc: true,
+            }],
+            fields: vec!["in".to_string(), "a b".to_string(), "name".to_string()],
+            ..Default::default()
+        };
+        let printed = query.to_string();
+        assert_eq!(
+            printed,
+            "in = 1 order by `order by` desc select `in`,`a b`,name"
+        );
+        match yacc_parse_query(&printed) {
+            Ok(parsed) => {
+                assert_eq!(parsed.fields, query.fields);
+                assert_eq!(parsed.sorts, query.sorts);
+            }
+            Err(e) => panic!("{}: {}", printed, e),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_tree() {
+        // 左侧为 && 的 && 需要括号，否则会按右结合解析
+        let expr = parse("(a = 1 && b = 2) && c = 3").unwrap();
+        let printed = expr.to_string();
+        assert_eq!(printed, "(a = 1 && b = 2) && c = 3");
+        match parse(&printed) {
+            Ok(Expr::And { lhs, .. }) => assert!(matches!(*lhs, Expr::And { .. })),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 右侧为 || 的 || 需要括号
+        let expr = parse("a = 1 || (b = 2 || c = 3)").unwrap();
+        let printed = expr.to_string();
+        assert_eq!(printed, "a = 1 || (b = 2 || c = 3)");
+        match parse(&printed) {
+            Ok(Expr::Or { rhs, .. }) => assert!(matches!(*rhs, Expr::Or { .. })),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+    }
+}
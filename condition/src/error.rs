@@ -0,0 +1,141 @@
+use std::fmt;
+
+use lrpar::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    Lexical,          // 无法识别的字符
+    Syntax,           // 不符合语法的 token
+    UnexpectedEnd,    // 表达式不完整
+    InvalidNumber,    // 非法数字，例如 1.2.3
+    InvalidBool,      // 非法布尔值
+    InvalidDateTime,  // 非法时间，例如 2022-13-01T00:00:00Z
+    InvalidEscape,    // 非法转义，例如 '\u12'
+    Empty,            // 空表达式
+    UnboundParam,     // 占位符缺少参数
+    InvalidParam,     // 参数类型与操作符不符
+    ExpectedField,    // 该操作符左侧只能是字段，例如 a + 1 = 'x'
+    UnknownFunction,  // 未注册的函数
+    InvalidArguments, // 函数参数个数或类型不符
+    InvalidFlags,     // 非法正则选项，例如 a ! 'x' g
+    UnknownField,     // schema 中未声明的字段
+    TypeMismatch,     // 字面量类型与字段声明的类型不符
+    InvalidOperator,  // 操作符不适用于字段类型，例如数字字段上的 len()
+    LimitExceeded,    // 超出 ParseLimits 的限制，found 为限制项
+    ForbiddenField,   // FieldPolicy 不允许的字段
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ParseErrorKind::Lexical => "lexical error",
+            ParseErrorKind::Syntax => "syntax error",
+            ParseErrorKind::UnexpectedEnd => "unexpected end of input",
+            ParseErrorKind::InvalidNumber => "invalid number",
+            ParseErrorKind::InvalidBool => "invalid bool",
+            ParseErrorKind::InvalidDateTime => "invalid datetime",
+            ParseErrorKind::InvalidEscape => "invalid escape",
+            ParseErrorKind::Empty => "empty expression",
+            ParseErrorKind::UnboundParam => "unbound parameter",
+            ParseErrorKind::InvalidParam => "invalid parameter",
+            ParseErrorKind::ExpectedField => "expected field, found",
+            ParseErrorKind::UnknownFunction => "unknown function",
+            ParseErrorKind::InvalidArguments => "invalid arguments for",
+            ParseErrorKind::InvalidFlags => "invalid flags",
+            ParseErrorKind::UnknownField => "unknown field",
+            ParseErrorKind::TypeMismatch => "type mismatch for",
+            ParseErrorKind::InvalidOperator => "invalid operator for",
+            ParseErrorKind::LimitExceeded => "limit exceeded for",
+            ParseErrorKind::ForbiddenField => "forbidden field",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// 条件解析错误
+// span: 出错位置在 input 中的字节区间
+// expected: 该位置可接受的 token
+// found: 出错位置的原始文本
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+    pub expected: Vec<String>,
+    pub found: String,
+    pub input: String,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, span: Span, found: &str) -> Self {
+        Self {
+            kind,
+            span,
+            expected: vec![],
+            found: found.to_string(),
+            input: String::new(),
+        }
+    }
+
+    pub fn with_expected(mut self, expected: Vec<String>) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    pub fn with_input(mut self, input: &str) -> Self {
+        self.input = input.to_string();
+        self
+    }
+
+    fn message(&self) -> String {
+        let mut msg = match self.kind {
+            ParseErrorKind::UnexpectedEnd | ParseErrorKind::Empty => self.kind.to_string(),
+            ParseErrorKind::Syntax => format!("unexpected `{}`", self.found),
+            _ => format!("{} `{}`", self.kind, self.found),
+        };
+        match self.expected.len() {
+            0 => {}
+            1 => msg.push_str(&format!(", expected {}", self.expected[0])),
+            _ => msg.push_str(&format!(", expected one of {}", self.expected.join(", "))),
+        }
+        msg
+    }
+
+    // 以插入符标记出错位置，例如:
+    // a = 1.2.3
+    //     ^^^^^ invalid number `1.2.3`
+    pub fn render(&self) -> String {
+        let start = self.span.start().min(self.input.len());
+        let end = self.span.end().clamp(start, self.input.len());
+
+        let line_start = self.input[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.input[start..]
+            .find('\n')
+            .map_or(self.input.len(), |i| start + i);
+        let line = &self.input[line_start..line_end];
+
+        let column = self.input[line_start..start].chars().count();
+        let width = self.input[start..end.min(line_end)].chars().count().max(1);
+
+        format!(
+            "{}\n{}{} {}",
+            line,
+            " ".repeat(column),
+            "^".repeat(width),
+            self.message()
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message(),
+            self.span.start(),
+            self.span.end()
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
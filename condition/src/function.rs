@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+// 内置函数: 名称、最少参数个数、最多参数个数
+// len() 沿用原有的 Operand::Len，不在此列
+pub const BUILTINS: &[(&str, usize, usize)] = &[
+    ("lower", 1, 1),      // lower(name) = 'bill'
+    ("upper", 1, 1),      // upper(name) = 'BILL'
+    ("trim", 1, 1),       // trim(name) = 'bill'
+    ("exists", 1, 1),     // exists(a) = true，参数只能是字段
+    ("now", 0, 0),        // t < now()
+    ("date_trunc", 2, 2), // date_trunc('day', t) = 2022-06-13T00:00:00Z
+    ("abs", 1, 1),        // abs(a - b) < 3
+    ("size", 1, 1),       // size(tags) > 1，只统计数组
+    ("type", 1, 1),       // type(a) = 'number'
+];
+
+// date_trunc 支持的时间单位，week 从周日开始
+pub const DATE_UNITS: &[&str] = &[
+    "year", "quarter", "month", "week", "day", "hour", "minute", "second",
+];
+
+// 用户注册的函数，解析时只检查函数名和参数个数，求值由各个后端负责
+static FUNCTIONS: Lazy<RwLock<HashMap<String, (usize, usize)>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn is_builtin(name: &str) -> bool {
+    name.eq_ignore_ascii_case("len") || BUILTINS.iter().any(|(n, _, _)| *n == name)
+}
+
+// 注册同名的内置函数无效，返回 false
+pub fn register_function(name: &str, min_args: usize, max_args: usize) -> bool {
+    if is_builtin(name) {
+        return false;
+    }
+    FUNCTIONS
+        .write()
+        .unwrap()
+        .insert(name.to_string(), (min_args, max_args));
+    true
+}
+
+// 函数的参数个数范围，未知函数返回 None
+pub fn function_arity(name: &str) -> Option<(usize, usize)> {
+    if let Some((_, min, max)) = BUILTINS.iter().find(|(n, _, _)| *n == name) {
+        return Some((*min, *max));
+    }
+    FUNCTIONS.read().unwrap().get(name).copied()
+}
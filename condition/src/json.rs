@@ -0,0 +1,317 @@
+use lrpar::Span;
+use serde::de::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+
+use crate::{call, parse_flags, value_field};
+use crate::{Bound, Expr, Operand, ParseError, ParseErrorKind, ParseLimits, Value};
+
+// JSON 编码的版本，节点结构不兼容地变化时递增
+pub const JSON_VERSION: u32 = 1;
+
+pub(crate) fn no_span() -> Span {
+    Span::new(0, 0)
+}
+
+pub(crate) fn is_empty(span: &Span) -> bool {
+    span.start() == 0 && span.end() == 0
+}
+
+// {"version": 1, "expr": {"op": "eq", "field": "a", "value": {"number": 1}}}
+#[derive(Serialize, Deserialize)]
+struct Document {
+    version: u32,
+    expr: Expr,
+}
+
+fn strip_spans(json: &mut Json) {
+    match json {
+        Json::Object(map) => {
+            map.remove("span");
+            map.values_mut().for_each(strip_spans);
+        }
+        Json::Array(items) => items.iter_mut().for_each(strip_spans),
+        _ => {}
+    }
+}
+
+impl Expr {
+    // spans 为 false 时不输出出错位置，便于前端构造或持久化
+    pub fn to_json(&self, spans: bool) -> Json {
+        let mut json = serde_json::to_value(Document {
+            version: JSON_VERSION,
+            expr: self.clone(),
+        })
+        .expect("expr is always serializable");
+        if !spans {
+            strip_spans(&mut json);
+        }
+        json
+    }
+
+    // 检查版本，并按条件语法校验字段名、值的类型、函数和正则选项，与解析得到的表达式一样可用于 MongoFilter 和 matchs
+    // 不受 default_limits() 限制，来自不可信来源时使用 from_json_with
+    pub fn from_json(json: Json) -> serde_json::Result<Expr> {
+        let doc: Document = serde_json::from_value(json)?;
+        if doc.version != JSON_VERSION {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported condition version {}, expected {}",
+                doc.version, JSON_VERSION
+            )));
+        }
+        validate(&doc.expr).map_err(serde_json::Error::custom)?;
+        Ok(doc.expr)
+    }
+
+    // 同 from_json，并按 limits 检查规模
+    pub fn from_json_with(json: Json, limits: &ParseLimits) -> serde_json::Result<Expr> {
+        let expr = Self::from_json(json)?;
+        expr.check_limits(limits)
+            .map_err(serde_json::Error::custom)?;
+        Ok(expr)
+    }
+
+    pub fn from_json_str(s: &str) -> serde_json::Result<Expr> {
+        Self::from_json(serde_json::from_str(s)?)
+    }
+}
+
+// 字段名不能为空，其余字符在条件中都可以通过反引号写出
+fn check_field(span: Span, field: &str) -> Result<(), ParseError> {
+    match field.is_empty() {
+        true => Err(ParseError::new(ParseErrorKind::ExpectedField, span, field)),
+        false => Ok(()),
+    }
+}
+
+fn expected(span: Span, value: &Value, expected: &str) -> ParseError {
+    ParseError::new(ParseErrorKind::Syntax, span, &value.to_string())
+        .with_expected(vec![expected.to_string()])
+}
+
+// 比较右侧的值，与另一个字段比较时字段名与条件中的规则相同
+fn check_scalar(span: Span, value: &Value, bool: bool) -> Result<(), ParseError> {
+    match value {
+        Value::Text(_) | Value::Number(_) | Value::DateTime(_) | Value::Param(_) => Ok(()),
+        Value::Bool(_) if bool => Ok(()),
+        Value::Field(field) => {
+            check_field(span, field)?;
+            value_field(span, &Operand::Field(field.clone()))
+        }
+        value if bool => Err(expected(
+            span,
+            value,
+            "text, number, bool, datetime or field",
+        )),
+        value => Err(expected(span, value, "text, number, datetime or field")),
+    }
+}
+
+fn check_list(span: Span, value: &Value) -> Result<(), ParseError> {
+    let scalar = |v: &Value| {
+        matches!(
+            v,
+            Value::Text(_) | Value::Number(_) | Value::Bool(_) | Value::Null | Value::DateTime(_)
+        )
+    };
+    match value {
+        Value::Param(_) => Ok(()),
+        Value::List(items) if items.iter().all(scalar) => Ok(()),
+        value => Err(expected(
+            span,
+            value,
+            "list of text, number, bool, null or datetime",
+        )),
+    }
+}
+
+fn check_text(span: Span, value: &Value) -> Result<(), ParseError> {
+    match value {
+        Value::Text(_) | Value::Param(_) => Ok(()),
+        value => Err(expected(span, value, "text")),
+    }
+}
+
+fn check_bound(span: Span, bound: &Bound) -> Result<(), ParseError> {
+    match bound.value() {
+        Value::Text(_) | Value::Number(_) | Value::DateTime(_) | Value::Param(_) => Ok(()),
+        value => Err(expected(span, value, "text, number or datetime")),
+    }
+}
+
+// 函数名和参数个数按注册的函数检查，与解析时相同
+fn check_operand(span: Span, operand: &Operand) -> Result<(), ParseError> {
+    match operand {
+        Operand::Field(field) | Operand::Len(field) => check_field(span, field),
+        Operand::Value(
+            Value::Text(_)
+            | Value::Number(_)
+            | Value::Bool(_)
+            | Value::DateTime(_)
+            | Value::Param(_),
+        ) => Ok(()),
+        Operand::Value(value) => Err(expected(span, value, "text, number, bool or datetime")),
+        Operand::Arith { lhs, rhs, .. } => {
+            check_operand(span, lhs)?;
+            check_operand(span, rhs)
+        }
+        Operand::Call { name, args } => {
+            args.iter().try_for_each(|arg| check_operand(span, arg))?;
+            call(span, name.clone(), args.clone()).map(|_| ())
+        }
+    }
+}
+
+// 直接检查解码得到的表达式树，不经过输出和重新解析
+fn validate(expr: &Expr) -> Result<(), ParseError> {
+    let span = expr.span();
+    match expr {
+        Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+            validate(lhs)?;
+            validate(rhs)
+        }
+        Expr::Not { expr, .. } => validate(expr),
+        Expr::Any { field, expr, .. } | Expr::All { field, expr, .. } => {
+            check_field(span, field)?;
+            validate(expr)
+        }
+        Expr::Eq { field, value, .. } | Expr::Ne { field, value, .. } => {
+            check_field(span, field)?;
+            check_scalar(span, value, true)
+        }
+        Expr::Gt { field, value, .. }
+        | Expr::Gte { field, value, .. }
+        | Expr::Lt { field, value, .. }
+        | Expr::Lte { field, value, .. } => {
+            check_field(span, field)?;
+            check_scalar(span, value, false)
+        }
+        Expr::Like { field, value, .. } | Expr::NotLike { field, value, .. } => {
+            check_field(span, field)?;
+            check_text(span, value)
+        }
+        Expr::In { field, value, .. }
+        | Expr::NotIn { field, value, .. }
+        | Expr::Belong { field, value, .. }
+        | Expr::NoBelong { field, value, .. }
+        | Expr::Has { field, value, .. } => {
+            check_field(span, field)?;
+            check_list(span, value)
+        }
+        Expr::IsNull { field, .. } | Expr::IsNotNull { field, .. } => check_field(span, field),
+        Expr::Len { field, value, .. } => {
+            check_field(span, field)?;
+            match value {
+                Value::Number(_) | Value::Param(_) => Ok(()),
+                value => Err(expected(span, value, "number")),
+            }
+        }
+        Expr::Compare { lhs, rhs, .. } => {
+            check_operand(span, lhs)?;
+            check_operand(span, rhs)?;
+            value_field(span, rhs)
+        }
+        Expr::Between {
+            field,
+            lower,
+            upper,
+            ..
+        } => {
+            check_field(span, field)?;
+            check_bound(span, lower)?;
+            check_bound(span, upper)
+        }
+        Expr::Match {
+            field,
+            value,
+            flags,
+            ..
+        }
+        | Expr::NotMatch {
+            field,
+            value,
+            flags,
+            ..
+        } => {
+            check_field(span, field)?;
+            check_text(span, value)?;
+            parse_flags(flags, span).map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{field, yacc_parse as parse, Expr, ParseLimits, Value};
+
+    #[test]
+    fn test_json() {
+        let expr = field("age").gt(3).and(field("name").is_in(["a"]));
+        assert_eq!(
+            expr.to_json(true),
+            json!({"version": 1, "expr": {
+                "op": "and",
+                "lhs": {"op": "gt", "field": "age", "value": {"number": 3}},
+                "rhs": {"op": "in", "field": "name", "value": {"list": [{"text": "a"}]}},
+            }})
+        );
+
+        let s = "a = 1 && (b ~ ('x', 2022-06-13T00:00:00Z) || not c ^^ null) && len(d) > :n \
+                 && price * (1 + tax) > lower(e) && any(items, n between [1, 5)) \
+                 && name contains 'x' i && t has (1, 2) && f = g";
+        let expr = parse(s).unwrap();
+        let json = expr.to_json(true);
+        assert_eq!(json["expr"]["span"], json!({"start": 0, "end": s.len()}));
+
+        let decoded = Expr::from_json(json).unwrap();
+        assert_eq!(decoded.to_string(), expr.to_string());
+        assert_eq!(decoded.span(), expr.span());
+
+        let json = expr.to_json(false);
+        assert!(!json.to_string().contains("span"));
+        let decoded = Expr::from_json(json).unwrap();
+        assert_eq!(decoded.to_string(), expr.to_string());
+    }
+
+    #[test]
+    fn test_json_error() {
+        let cases = [
+            json!({"version": 2, "expr": {"op": "is_null", "field": "a"}}),
+            json!({"version": 1, "expr": {"op": "is_null", "field": ""}}),
+            json!({"version": 1, "expr": {"op": "match", "field": "a", "kind": "regex",
+                   "value": {"text": "x"}, "flags": "g"}}),
+            json!({"version": 1, "expr": {"op": "nope", "field": "a"}}),
+            json!({"expr": {"op": "is_null", "field": "a"}}),
+            json!({"version": 1, "expr": {"op": "in", "field": "a", "value": {"number": 1}}}),
+            json!({"version": 1, "expr": {"op": "gt", "field": "a", "value": {"bool": true}}}),
+            json!({"version": 1, "expr": {"op": "eq", "field": "a", "value": {"field": "1x"}}}),
+            json!({"version": 1, "expr": {"op": "in", "field": "a",
+                   "value": {"list": [{"list": []}]}}}),
+            json!({"version": 1, "expr": {"op": "compare", "cmp": "eq",
+                   "lhs": {"call": {"name": "nope", "args": []}}, "rhs": {"value": {"number": 1}}}}),
+            json!({"version": 1, "expr": {"op": "compare", "cmp": "eq",
+                   "lhs": {"call": {"name": "lower", "args": []}}, "rhs": {"value": {"text": "a"}}}}),
+        ];
+        for json in cases {
+            assert!(Expr::from_json(json.clone()).is_err(), "{}", json);
+        }
+
+        // 校验表达式树本身，不依赖输出后重新解析，也不受 default_limits() 限制
+        let expr = field("a b")
+            .is_in(Vec::<i32>::new())
+            .and(field("flag").is_in([Value::Bool(true), Value::Null]))
+            .and(field("id").is_in(0..2000));
+        let decoded = Expr::from_json(expr.to_json(false)).unwrap();
+        assert_eq!(decoded.to_json(false), expr.to_json(false));
+        let limits = ParseLimits::default();
+        match Expr::from_json_with(expr.to_json(false), &limits) {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => assert!(e.to_string().contains("list length"), "{}", e),
+        }
+        assert!(
+            Expr::from_json_str(r#"{"version":1,"expr":{"op":"is_null","field":"a"}}"#).is_ok()
+        );
+    }
+}
@@ -1,16 +1,289 @@
 pub mod cond;
 pub use cond::*;
 
-fn remove_apostrophe(s: String) -> String {
-    if s.starts_with(r#"'"#) {
-        s.trim_end_matches(r#"'"#)
-            .to_string()
-            .trim_start_matches("'")
-            .to_string()
-    } else {
-        s.trim_end_matches(r#"""#)
-            .to_string()
-            .trim_start_matches(r#"""#)
-            .to_string()
+mod error;
+pub use error::{ParseError, ParseErrorKind};
+
+mod display;
+
+mod normalize;
+
+mod params;
+pub use params::Params;
+
+pub mod function;
+pub use function::register_function;
+
+mod schema;
+pub use schema::{FieldType, Schema};
+
+pub mod builder;
+pub use builder::field;
+
+mod json;
+pub use json::JSON_VERSION;
+
+mod limits;
+pub use limits::{default_limits, set_default_limits, ParseLimits};
+
+mod policy;
+pub use policy::FieldPolicy;
+
+pub mod visit;
+pub use visit::{Fold, Visitor, VisitorMut};
+
+mod query;
+pub use query::{OrderBy, Query};
+
+use chrono::{DateTime, TimeZone, Utc};
+use lrlex::{DefaultLexeme, DefaultLexerTypes};
+use lrpar::{Lexeme, NonStreamingLexer, Span};
+use serde_json::Number;
+
+// 不含算术运算的比较保持原有的表达式: a > 1、a > b、len(a) > 1
+fn compare(span: Span, lhs: Operand, cmp: Compare, rhs: Operand) -> Result<Expr, ParseError> {
+    value_field(span, &rhs)?;
+    Ok(match (lhs, rhs) {
+        (Operand::Field(field), Operand::Value(value)) => simple(span, field, cmp, value),
+        (Operand::Field(field), Operand::Field(other)) => {
+            simple(span, field, cmp, Value::Field(other))
+        }
+        (Operand::Len(field), Operand::Value(value)) => Expr::Len {
+            span,
+            field,
+            cmp,
+            value,
+        },
+        (lhs, rhs) => Expr::Compare {
+            span,
+            lhs,
+            cmp,
+            rhs,
+        },
+    })
+}
+
+// 右侧的 2022-06-13、1-2 会被识别为字段，通常是漏写了引号或空格，不作为字段引用
+fn value_field(span: Span, operand: &Operand) -> Result<(), ParseError> {
+    match operand {
+        Operand::Field(field)
+            if field.starts_with(|c: char| c.is_ascii_digit()) || field.contains('-') =>
+        {
+            Err(ParseError::new(ParseErrorKind::ExpectedField, span, field))
+        }
+        Operand::Arith { lhs, rhs, .. } => {
+            value_field(span, lhs)?;
+            value_field(span, rhs)
+        }
+        Operand::Call { args, .. } => args.iter().try_for_each(|arg| value_field(span, arg)),
+        _ => Ok(()),
+    }
+}
+
+fn simple(span: Span, field: String, cmp: Compare, value: Value) -> Expr {
+    match cmp {
+        Compare::Eq => Expr::Eq { span, field, value },
+        Compare::Ne => Expr::Ne { span, field, value },
+        Compare::Gt => Expr::Gt { span, field, value },
+        Compare::Gte => Expr::Gte { span, field, value },
+        Compare::Lt => Expr::Lt { span, field, value },
+        Compare::Lte => Expr::Lte { span, field, value },
+    }
+}
+
+// 与文本、布尔、时间比较时左侧只能是字段或函数调用，例如 lower(name) = 'bill'
+fn compare_value(span: Span, lhs: Operand, cmp: Compare, value: Value) -> Result<Expr, ParseError> {
+    match lhs {
+        Operand::Field(field) => Ok(simple(span, field, cmp, value)),
+        lhs @ Operand::Call { .. } => Ok(Expr::Compare {
+            span,
+            lhs,
+            cmp,
+            rhs: Operand::Value(value),
+        }),
+        lhs => Err(ParseError::new(
+            ParseErrorKind::ExpectedField,
+            span,
+            &lhs.to_string(),
+        )),
+    }
+}
+
+// 模糊匹配、列表、空值比较的左侧只能是字段
+fn field_of(span: Span, operand: Operand) -> Result<String, ParseError> {
+    match operand {
+        Operand::Field(field) => Ok(field),
+        operand => Err(ParseError::new(
+            ParseErrorKind::ExpectedField,
+            span,
+            &operand.to_string(),
+        )),
+    }
+}
+
+// 检查函数名和参数个数，len(field) 保持为 Operand::Len
+fn call(span: Span, name: String, args: Vec<Operand>) -> Result<Operand, ParseError> {
+    let invalid = |expected: &str| {
+        ParseError::new(ParseErrorKind::InvalidArguments, span, &name)
+            .with_expected(vec![expected.to_string()])
+    };
+    if name.eq_ignore_ascii_case("len") {
+        return match args.as_slice() {
+            [Operand::Field(field)] => Ok(Operand::Len(field.clone())),
+            _ => Err(invalid("a single field")),
+        };
+    }
+    let (min, max) = match function::function_arity(&name) {
+        Some(arity) => arity,
+        None => {
+            return Err(ParseError::new(
+                ParseErrorKind::UnknownFunction,
+                span,
+                &name,
+            ))
+        }
+    };
+    if args.len() < min || args.len() > max {
+        return Err(invalid(&match (min, max) {
+            (min, max) if min == max => format!("{} argument(s)", min),
+            (min, max) => format!("{} to {} arguments", min, max),
+        }));
     }
+    match (name.as_str(), args.first()) {
+        ("exists", Some(Operand::Field(_))) => {}
+        ("exists", _) => return Err(invalid("a field")),
+        ("date_trunc", Some(Operand::Value(Value::Text(unit))))
+            if !function::DATE_UNITS.contains(&unit.as_str()) =>
+        {
+            return Err(invalid(&format!(
+                "time unit one of {}",
+                function::DATE_UNITS.join(", ")
+            )))
+        }
+        _ => {}
+    }
+    Ok(Operand::Call { name, args })
+}
+
+// 正则选项只支持 i 和 m，输出时按字母排序去重
+fn parse_flags(s: &str, span: Span) -> Result<String, ParseError> {
+    let mut flags: Vec<char> = s.chars().collect();
+    if flags.iter().any(|c| !matches!(c, 'i' | 'm')) {
+        return Err(ParseError::new(ParseErrorKind::InvalidFlags, span, s)
+            .with_expected(vec!["i, m".to_string()]));
+    }
+    flags.sort_unstable();
+    flags.dedup();
+    Ok(flags.into_iter().collect())
+}
+
+type Token = Result<DefaultLexeme<u32>, DefaultLexeme<u32>>;
+
+// 去掉首尾引号并处理转义: \' \" \\ \n \r \t \uXXXX
+// 其余反斜杠原样保留，正则中的 \d、\. 等无需写成 \\d
+fn unescape(src: &str, span: Span) -> Result<String, ParseError> {
+    let inner = &src[1..src.len() - 1];
+    let mut rs = String::with_capacity(inner.len());
+    let mut chars = inner.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            rs.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, c @ ('\'' | '"' | '`' | '\\'))) => rs.push(c),
+            Some((_, 'n')) => rs.push('\n'),
+            Some((_, 'r')) => rs.push('\r'),
+            Some((_, 't')) => rs.push('\t'),
+            Some((_, 'u')) => {
+                let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) if hex.len() == 4 => rs.push(c),
+                    _ => {
+                        // 出错位置为 \u 转义在原文中的区间，+1 跳过开头的引号
+                        let start = span.start() + 1 + i;
+                        let end = (start + 2 + hex.len()).min(span.end());
+                        return Err(ParseError::new(
+                            ParseErrorKind::InvalidEscape,
+                            Span::new(start, end),
+                            &format!("\\u{}", hex),
+                        ));
+                    }
+                }
+            }
+            Some((_, c)) => {
+                rs.push('\\');
+                rs.push(c);
+            }
+            None => rs.push('\\'),
+        }
+    }
+    Ok(rs)
+}
+
+// 错误恢复时插入的 token 在原文中不存在，不能取文本
+fn token_str<'input>(
+    lexer: &dyn NonStreamingLexer<'input, DefaultLexerTypes<u32>>,
+    token: &Token,
+) -> Result<(&'input str, Span), ParseError> {
+    match token {
+        Ok(lexeme) => Ok((lexer.span_str(lexeme.span()), lexeme.span())),
+        Err(lexeme) => Err(ParseError::new(ParseErrorKind::Syntax, lexeme.span(), "")),
+    }
+}
+
+// 反引号中的字段名按字符串转义，不能为空
+fn quoted_field(s: &str, span: Span) -> Result<String, ParseError> {
+    match unescape(s, span)? {
+        field if field.is_empty() => Err(ParseError::new(ParseErrorKind::ExpectedField, span, s)),
+        field => Ok(field),
+    }
+}
+
+// in、is 的符号写法 ~ ^ 不能作为字段名
+fn keyword_field(s: &str, span: Span) -> Result<String, ParseError> {
+    match s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        true => Ok(s.to_string()),
+        false => Err(ParseError::new(ParseErrorKind::Syntax, span, s)),
+    }
+}
+
+fn parse_number(s: &str, span: Span) -> Result<Number, ParseError> {
+    s.parse::<Number>()
+        .map_err(|_| ParseError::new(ParseErrorKind::InvalidNumber, span, s))
+}
+
+// limit、offset 只接受非负整数
+fn parse_count(s: &str, span: Span) -> Result<usize, ParseError> {
+    s.parse::<usize>().map_err(|_| {
+        ParseError::new(ParseErrorKind::InvalidNumber, span, s)
+            .with_expected(vec!["non-negative integer".to_string()])
+    })
+}
+
+fn order_by(field: String, s: &str, span: Span) -> Result<OrderBy, ParseError> {
+    match s.to_ascii_lowercase().as_str() {
+        "asc" => Ok(OrderBy { field, desc: false }),
+        "desc" => Ok(OrderBy { field, desc: true }),
+        _ => Err(ParseError::new(ParseErrorKind::Syntax, span, s)
+            .with_expected(vec!["asc".to_string(), "desc".to_string()])),
+    }
+}
+
+fn parse_bool(s: &str, span: Span) -> Result<bool, ParseError> {
+    s.parse::<bool>()
+        .map_err(|_| ParseError::new(ParseErrorKind::InvalidBool, span, s))
+}
+
+// 2022-06-13T16:00:00+08:00、2022-06-13 16:00:00Z、@1655136000(秒)
+fn parse_datetime(s: &str, span: Span) -> Result<Value, ParseError> {
+    let err = || ParseError::new(ParseErrorKind::InvalidDateTime, span, s);
+    let dt = match s.strip_prefix('@') {
+        Some(secs) => {
+            let secs = secs.parse::<i64>().map_err(|_| err())?;
+            Utc.timestamp_opt(secs, 0).single().ok_or_else(err)?.into()
+        }
+        None => DateTime::parse_from_rfc3339(&s.replacen(' ', "T", 1)).map_err(|_| err())?,
+    };
+    Ok(Value::DateTime(dt))
 }
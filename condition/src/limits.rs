@@ -0,0 +1,328 @@
+use std::mem::discriminant;
+use std::sync::RwLock;
+
+use lrpar::Span;
+use once_cell::sync::Lazy;
+
+use crate::{Expr, Operand, ParseError, ParseErrorKind, Value};
+
+// 条件的规模限制，用于来自 HTTP 等不可信来源的条件，超出时返回 LimitExceeded
+// ParseLimits { max_depth: 8, ..Default::default() }
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    pub max_input_len: usize,     // 条件原文的字节数
+    pub max_depth: usize,         // 嵌套层数，同一层的 && 或 || 连接不增加层数
+    pub max_terms: usize,         // 比较、匹配等条件项的个数
+    pub max_list_len: usize,      // 列表中值的个数，包括绑定的参数
+    pub max_regex_len: usize,     // 正则的长度，contains 等按转义后的正则计算
+    pub max_regex_repeats: usize, // 正则中 * + ? {n} 等重复的个数
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_input_len: 64 * 1024,
+            max_depth: 32,
+            max_terms: 512,
+            max_list_len: 1000,
+            max_regex_len: 512,
+            max_regex_repeats: 16,
+        }
+    }
+}
+
+// 默认不做限制，不可信来源的条件通过 Condition::with_limits 或 set_default_limits 限制
+static DEFAULT_LIMITS: Lazy<RwLock<ParseLimits>> =
+    Lazy::new(|| RwLock::new(ParseLimits::unlimited()));
+
+// 设置 yacc_parse、yacc_parse_with 使用的限制，进程内全局生效
+pub fn set_default_limits(limits: ParseLimits) {
+    *DEFAULT_LIMITS.write().unwrap() = limits;
+}
+
+pub fn default_limits() -> ParseLimits {
+    DEFAULT_LIMITS.read().unwrap().clone()
+}
+
+fn exceeded(span: Span, name: &str, limit: usize) -> ParseError {
+    ParseError::new(ParseErrorKind::LimitExceeded, span, name)
+        .with_expected(vec![format!("at most {}", limit)])
+}
+
+impl ParseLimits {
+    // 不做任何限制，用于代码中可信的条件
+    pub fn unlimited() -> Self {
+        Self {
+            max_input_len: usize::MAX,
+            max_depth: usize::MAX,
+            max_terms: usize::MAX,
+            max_list_len: usize::MAX,
+            max_regex_len: usize::MAX,
+            max_regex_repeats: usize::MAX,
+        }
+    }
+
+    // 在词法分析之前检查原文长度
+    pub fn check_input(&self, input: &str) -> Result<(), ParseError> {
+        if input.len() > self.max_input_len {
+            return Err(exceeded(
+                Span::new(0, input.len()),
+                "input length",
+                self.max_input_len,
+            )
+            .with_input(input));
+        }
+        Ok(())
+    }
+}
+
+// 统计条件项个数，并在遍历时检查嵌套层数
+struct Counter<'a> {
+    limits: &'a ParseLimits,
+    terms: usize,
+}
+
+impl Counter<'_> {
+    fn expr(&mut self, expr: &Expr, depth: usize) -> Result<(), ParseError> {
+        // a && b && c 右结合为 And(a, And(b, c))，同类连接视为同一层
+        let nested = |child: &Expr| match discriminant(child) == discriminant(expr) {
+            true => depth,
+            false => depth + 1,
+        };
+        // 只有 && || not any() all() 这些组合条件计入层数
+        let compound = matches!(
+            expr,
+            Expr::And { .. }
+                | Expr::Or { .. }
+                | Expr::Not { .. }
+                | Expr::Any { .. }
+                | Expr::All { .. }
+        );
+        if compound && depth > self.limits.max_depth {
+            return Err(exceeded(expr.span(), "depth", self.limits.max_depth));
+        }
+        match expr {
+            Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+                self.expr(lhs, nested(lhs))?;
+                return self.expr(rhs, nested(rhs));
+            }
+            Expr::Not { expr, .. } | Expr::Any { expr, .. } | Expr::All { expr, .. } => {
+                return self.expr(expr, depth + 1);
+            }
+            _ => {}
+        }
+
+        self.terms += 1;
+        if self.terms > self.limits.max_terms {
+            return Err(exceeded(expr.span(), "terms", self.limits.max_terms));
+        }
+        let span = expr.span();
+        match expr {
+            Expr::Eq { value, .. }
+            | Expr::Ne { value, .. }
+            | Expr::Gt { value, .. }
+            | Expr::Gte { value, .. }
+            | Expr::Lt { value, .. }
+            | Expr::Lte { value, .. }
+            | Expr::In { value, .. }
+            | Expr::NotIn { value, .. }
+            | Expr::Len { value, .. }
+            | Expr::Belong { value, .. }
+            | Expr::NoBelong { value, .. }
+            | Expr::Has { value, .. } => self.value(span, value),
+            Expr::Like {
+                value: Value::Text(pattern),
+                ..
+            }
+            | Expr::NotLike {
+                value: Value::Text(pattern),
+                ..
+            }
+            | Expr::NotMatch {
+                value: Value::Text(pattern),
+                ..
+            } => self.regex(span, pattern),
+            Expr::Match {
+                op,
+                value: Value::Text(text),
+                ..
+            } => self.regex(span, &op.pattern(text)),
+            Expr::Between { lower, upper, .. } => {
+                self.value(span, lower.value())?;
+                self.value(span, upper.value())
+            }
+            Expr::Compare { lhs, rhs, .. } => {
+                self.operand(span, lhs, depth)?;
+                self.operand(span, rhs, depth)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn value(&self, span: Span, value: &Value) -> Result<(), ParseError> {
+        match value {
+            Value::List(items) if items.len() > self.limits.max_list_len => {
+                Err(exceeded(span, "list length", self.limits.max_list_len))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // 算术运算和函数调用的每一层嵌套都计入层数
+    fn operand(&self, span: Span, operand: &Operand, depth: usize) -> Result<(), ParseError> {
+        let compound = matches!(operand, Operand::Arith { .. } | Operand::Call { .. });
+        if compound && depth > self.limits.max_depth {
+            return Err(exceeded(span, "depth", self.limits.max_depth));
+        }
+        match operand {
+            Operand::Arith { lhs, rhs, .. } => {
+                self.operand(span, lhs, depth + 1)?;
+                self.operand(span, rhs, depth + 1)
+            }
+            Operand::Call { args, .. } => args
+                .iter()
+                .try_for_each(|arg| self.operand(span, arg, depth + 1)),
+            Operand::Value(value) => self.value(span, value),
+            _ => Ok(()),
+        }
+    }
+
+    fn regex(&self, span: Span, pattern: &str) -> Result<(), ParseError> {
+        if pattern.len() > self.limits.max_regex_len {
+            return Err(exceeded(span, "regex length", self.limits.max_regex_len));
+        }
+        if repeats(pattern) > self.limits.max_regex_repeats {
+            return Err(exceeded(
+                span,
+                "regex repeats",
+                self.limits.max_regex_repeats,
+            ));
+        }
+        Ok(())
+    }
+}
+
+// 统计正则中的重复，跳过转义字符、字符类 [...] 以及 (?i) 这类分组选项
+fn repeats(pattern: &str) -> usize {
+    let mut count = 0;
+    let mut class = false;
+    let mut prev = None;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => class = true,
+            ']' => class = false,
+            '?' if prev == Some('(') => {}
+            '*' | '+' | '?' | '{' if !class => count += 1,
+            _ => {}
+        }
+        prev = Some(c);
+    }
+    count
+}
+
+impl Expr {
+    // 检查嵌套层数、条件项个数、列表长度和正则规模，构造或 JSON 解码的表达式同样适用
+    pub fn check_limits(&self, limits: &ParseLimits) -> Result<(), ParseError> {
+        Counter { limits, terms: 0 }.expr(self, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_limits, ParseLimits};
+    use crate::{field, yacc_parse, ParseErrorKind};
+
+    fn limits() -> ParseLimits {
+        ParseLimits {
+            max_input_len: 64,
+            max_depth: 3,
+            max_terms: 4,
+            max_list_len: 3,
+            max_regex_len: 8,
+            max_regex_repeats: 2,
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = limits();
+        for s in [
+            "a = 1 && b = 2 && c = 3 && d = 4",
+            "a = 1 && (b = 2 || !(c = 3))",
+            "a ~ (1, 2, 3)",
+            "a ! '^x+y*$'",
+            "a ! '[*+?]x'",
+            "a ! '(?i)x+y'",
+            "a contains '.*+'",
+        ] {
+            let expr = yacc_parse(s).unwrap();
+            assert!(expr.check_limits(&limits).is_ok(), "{}", s);
+        }
+
+        for (s, found) in [
+            ("a = 1 && b = 2 && c = 3 && d = 4 && e = 5", "terms"),
+            ("a = 1 && (b = 2 || !(c = 3 && d = 4))", "depth"),
+            ("any(a, not (b = 1 || any(c, d = 1)))", "depth"),
+            ("a + (b * (c - (d / 2))) > 1", "depth"),
+            ("a ~ (1, 2, 3, 4)", "list length"),
+            ("a ! '^abcdefgh$'", "regex length"),
+            ("a ! 'x+y+z+'", "regex repeats"),
+            ("a endswith 'abcdefgh'", "regex length"),
+        ] {
+            match yacc_parse(s).unwrap().check_limits(&limits) {
+                Ok(_) => panic!("unexpected ok {}", s),
+                Err(e) => {
+                    assert_eq!(e.kind, ParseErrorKind::LimitExceeded, "{}", s);
+                    assert_eq!(e.found, found, "{}", s);
+                }
+            }
+        }
+
+        let e = field("a")
+            .is_in([1, 2, 3, 4])
+            .check_limits(&limits)
+            .unwrap_err();
+        assert_eq!(e.found, "list length");
+        assert!(field("a")
+            .is_in([1, 2, 3, 4])
+            .check_limits(&ParseLimits::unlimited())
+            .is_ok());
+
+        let e = limits.check_input(&"a".repeat(65)).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::LimitExceeded);
+        assert_eq!(e.found, "input length");
+    }
+
+    #[test]
+    fn test_default_unlimited() {
+        // 全局默认不限制，已有的大条件不受影响
+        assert_eq!(default_limits(), ParseLimits::unlimited());
+        let list = (0..2000).map(|i| i.to_string()).collect::<Vec<_>>();
+        let terms = (0..600).map(|i| format!("a{} = 1", i)).collect::<Vec<_>>();
+        for s in [
+            format!("a ~ ({})", list.join(",")),
+            terms.join(" && "),
+            format!("a ! '{}'", "x+".repeat(20)),
+        ] {
+            assert!(yacc_parse(&s).is_ok(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_limits_render() {
+        let s = "a = 1 && b ~ (1, 2, 3, 4)";
+        let e = yacc_parse(s)
+            .unwrap()
+            .check_limits(&limits())
+            .unwrap_err()
+            .with_input(s);
+        assert_eq!(
+            e.render(),
+            "a = 1 && b ~ (1, 2, 3, 4)\n         ^^^^^^^^^^^^^^^^ limit exceeded for `list length`, expected at most 3"
+        );
+    }
+}
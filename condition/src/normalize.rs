@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::{discriminant, Discriminant};
+
+use lrpar::Span;
+
+use crate::{Expr, Value};
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::And { span, .. }
+            | Expr::Or { span, .. }
+            | Expr::Not { span, .. }
+            | Expr::Eq { span, .. }
+            | Expr::Ne { span, .. }
+            | Expr::Gt { span, .. }
+            | Expr::Gte { span, .. }
+            | Expr::Lt { span, .. }
+            | Expr::Lte { span, .. }
+            | Expr::Like { span, .. }
+            | Expr::NotLike { span, .. }
+            | Expr::In { span, .. }
+            | Expr::NotIn { span, .. }
+            | Expr::IsNull { span, .. }
+            | Expr::IsNotNull { span, .. }
+            | Expr::Len { span, .. }
+            | Expr::Belong { span, .. }
+            | Expr::NoBelong { span, .. }
+            | Expr::Compare { span, .. }
+            | Expr::Any { span, .. }
+            | Expr::All { span, .. }
+            | Expr::Has { span, .. }
+            | Expr::Between { span, .. }
+            | Expr::Match { span, .. }
+            | Expr::NotMatch { span, .. } => *span,
+        }
+    }
+
+    // 展开 && 链，a && (b && c) => [a, b, c]
+    pub fn conjuncts(&self) -> Vec<&Expr> {
+        match self {
+            Expr::And { lhs, rhs, .. } => {
+                let mut terms = lhs.conjuncts();
+                terms.extend(rhs.conjuncts());
+                terms
+            }
+            expr => vec![expr],
+        }
+    }
+
+    // 展开 || 链，(a || b) || c => [a, b, c]
+    pub fn disjuncts(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Or { lhs, rhs, .. } => {
+                let mut terms = lhs.disjuncts();
+                terms.extend(rhs.disjuncts());
+                terms
+            }
+            expr => vec![expr],
+        }
+    }
+
+    // 规范化: 展开 &&/|| 链、去除重复项、消除双重否定，
+    // 并将同一字段的 a = 1 || a = 2 || a ~ (3) 合并为 a ~ (1,2,3)
+    // 输出的树与不带括号书写时的解析结果形状一致
+    pub fn normalize(self) -> Expr {
+        match self {
+            expr @ Expr::And { .. } => {
+                let terms = expr
+                    .into_conjuncts()
+                    .into_iter()
+                    .flat_map(|e| e.normalize().into_conjuncts())
+                    .collect();
+                and_all(dedupe(terms))
+            }
+            expr @ Expr::Or { .. } => {
+                let terms = expr
+                    .into_disjuncts()
+                    .into_iter()
+                    .flat_map(|e| e.normalize().into_disjuncts())
+                    .collect();
+                or_all(fold_in(dedupe(terms)))
+            }
+            Expr::Not { span, expr } => match expr.normalize() {
+                Expr::Not { expr, .. } => *expr,
+                expr => Expr::Not {
+                    span,
+                    expr: Box::new(expr),
+                },
+            },
+            Expr::Any { span, field, expr } => Expr::Any {
+                span,
+                field,
+                expr: Box::new(expr.normalize()),
+            },
+            Expr::All { span, field, expr } => Expr::All {
+                span,
+                field,
+                expr: Box::new(expr.normalize()),
+            },
+            expr => expr,
+        }
+    }
+
+    // 析取范式: (a && b) || (c && d)
+    // 分配律展开后项数可能呈指数增长，只适合较小的条件
+    pub fn to_dnf(self) -> Expr {
+        let clauses = expand(self.push_not(false), true);
+        or_all(clauses.into_iter().map(and_all).collect()).normalize()
+    }
+
+    // 合取范式: (a || b) && (c || d)
+    pub fn to_cnf(self) -> Expr {
+        let clauses = expand(self.push_not(false), false);
+        and_all(clauses.into_iter().map(or_all).collect()).normalize()
+    }
+
+    fn into_conjuncts(self) -> Vec<Expr> {
+        match self {
+            Expr::And { lhs, rhs, .. } => {
+                let mut terms = lhs.into_conjuncts();
+                terms.extend(rhs.into_conjuncts());
+                terms
+            }
+            expr => vec![expr],
+        }
+    }
+
+    fn into_disjuncts(self) -> Vec<Expr> {
+        match self {
+            Expr::Or { lhs, rhs, .. } => {
+                let mut terms = lhs.into_disjuncts();
+                terms.extend(rhs.into_disjuncts());
+                terms
+            }
+            expr => vec![expr],
+        }
+    }
+
+    // 通过德摩根定律将取反下推到比较表达式
+    // 比较表达式本身不取反，!(a > 1) 与 a <= 1 在字段缺失时结果不同
+    fn push_not(self, negate: bool) -> Expr {
+        match self {
+            Expr::Not { expr, .. } => expr.push_not(!negate),
+            Expr::And { span, lhs, rhs } if negate => Expr::Or {
+                span,
+                lhs: Box::new(lhs.push_not(true)),
+                rhs: Box::new(rhs.push_not(true)),
+            },
+            Expr::Or { span, lhs, rhs } if negate => Expr::And {
+                span,
+                lhs: Box::new(lhs.push_not(true)),
+                rhs: Box::new(rhs.push_not(true)),
+            },
+            Expr::And { span, lhs, rhs } => Expr::And {
+                span,
+                lhs: Box::new(lhs.push_not(false)),
+                rhs: Box::new(rhs.push_not(false)),
+            },
+            Expr::Or { span, lhs, rhs } => Expr::Or {
+                span,
+                lhs: Box::new(lhs.push_not(false)),
+                rhs: Box::new(rhs.push_not(false)),
+            },
+            expr if negate => Expr::Not {
+                span: expr.span(),
+                expr: Box::new(expr),
+            },
+            expr => expr,
+        }
+    }
+}
+
+// dnf 为 true 时返回 [[a, b], [c]] 表示 (a && b) || c，否则表示 (a || b) && c
+fn expand(expr: Expr, dnf: bool) -> Vec<Vec<Expr>> {
+    match expr {
+        Expr::Or { lhs, rhs, .. } if dnf => {
+            let mut clauses = expand(*lhs, dnf);
+            clauses.extend(expand(*rhs, dnf));
+            clauses
+        }
+        Expr::And { lhs, rhs, .. } if !dnf => {
+            let mut clauses = expand(*lhs, dnf);
+            clauses.extend(expand(*rhs, dnf));
+            clauses
+        }
+        Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+            let (lhs, rhs) = (expand(*lhs, dnf), expand(*rhs, dnf));
+            let mut clauses = vec![];
+            for l in &lhs {
+                for r in &rhs {
+                    clauses.push(l.iter().chain(r.iter()).cloned().collect());
+                }
+            }
+            clauses
+        }
+        expr => vec![vec![expr]],
+    }
+}
+
+fn cover(lhs: &Expr, rhs: &Expr) -> Span {
+    let (l, r) = (lhs.span(), rhs.span());
+    Span::new(l.start().min(r.start()), l.end().max(r.end()))
+}
+
+// && 右结合: a && (b && c)
+fn and_all(terms: Vec<Expr>) -> Expr {
+    let mut iter = terms.into_iter().rev();
+    let last = iter.next().expect("and_all requires at least one term");
+    iter.fold(last, |rhs, lhs| Expr::And {
+        span: cover(&lhs, &rhs),
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    })
+}
+
+// || 左结合: (a || b) || c
+fn or_all(terms: Vec<Expr>) -> Expr {
+    let mut iter = terms.into_iter();
+    let first = iter.next().expect("or_all requires at least one term");
+    iter.fold(first, |lhs, rhs| Expr::Or {
+        span: cover(&lhs, &rhs),
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    })
+}
+
+// 输出可以往返解析，相同的输出即相同的语法树
+fn dedupe(terms: Vec<Expr>) -> Vec<Expr> {
+    let mut seen = HashSet::new();
+    terms
+        .into_iter()
+        .filter(|e| seen.insert(e.to_string()))
+        .collect()
+}
+
+// 可合并为 In 的项: 文本、数字或时间的 Eq，以及元素类型相同的 In
+// 返回字段、值类型和值列表，只有值类型相同的项才能合并
+fn in_values(expr: &Expr) -> Option<(&str, Discriminant<Value>, Vec<Value>)> {
+    let foldable = |v: &Value| matches!(v, Value::Text(_) | Value::Number(_) | Value::DateTime(_));
+    match expr {
+        Expr::Eq { field, value, .. } if foldable(value) => {
+            Some((field, discriminant(value), vec![value.clone()]))
+        }
+        Expr::In {
+            field,
+            value: Value::List(items),
+            ..
+        } => {
+            let kind = discriminant(items.first()?);
+            if items.iter().all(|v| foldable(v) && discriminant(v) == kind) {
+                Some((field, kind, items.clone()))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn fold_in(terms: Vec<Expr>) -> Vec<Expr> {
+    let mut out: Vec<Expr> = vec![];
+    let mut slots: HashMap<(String, Discriminant<Value>), usize> = HashMap::new();
+    for term in terms {
+        let (field, kind, values) = match in_values(&term) {
+            Some((field, kind, values)) => (field.to_string(), kind, values),
+            None => {
+                out.push(term);
+                continue;
+            }
+        };
+        let index = match slots.get(&(field.clone(), kind)) {
+            Some(index) => *index,
+            None => {
+                slots.insert((field, kind), out.len());
+                out.push(term);
+                continue;
+            }
+        };
+
+        let (_, _, mut merged) = in_values(&out[index]).expect("slot holds a foldable term");
+        for v in values {
+            if !merged.iter().any(|m| m.to_string() == v.to_string()) {
+                merged.push(v);
+            }
+        }
+        out[index] = Expr::In {
+            span: cover(&out[index], &term),
+            field,
+            value: Value::List(merged),
+        };
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{yacc_parse as parse, Expr};
+
+    fn normalize(s: &str) -> String {
+        parse(s).unwrap().normalize().to_string()
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("(a = 1 && b = 2) && (c = 3 && a = 1)"),
+            "a = 1 && b = 2 && c = 3"
+        );
+        assert_eq!(normalize("a = 1 || (b = 2 || a = 1)"), "a = 1 || b = 2");
+        assert_eq!(normalize("!(!(a = 1 && a = 1))"), "a = 1");
+        assert_eq!(normalize("(b = 2 && b = 2) || (b = 2 && b = 2)"), "b = 2");
+
+        // 规范化后的树与直接书写的树形状一致
+        match parse("(a = 1 && b = 2) && c = 3").unwrap().normalize() {
+            Expr::And { lhs, rhs, .. } => {
+                assert!(matches!(*lhs, Expr::Eq { .. }));
+                assert!(matches!(*rhs, Expr::And { .. }));
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+    }
+
+    #[test]
+    fn test_fold_in() {
+        assert_eq!(normalize("a = 1 || a = 2"), "a ~ (1,2)");
+        assert_eq!(
+            normalize("(a = 1 && b = 2) && a = 1 || a = 3 || a = 4"),
+            "a = 1 && b = 2 || a ~ (3,4)"
+        );
+        assert_eq!(
+            normalize("a = 1 || b = 'x' || a ~ (2,3) || a = 1 || b = 'y'"),
+            "a ~ (1,2,3) || b ~ ('x','y')"
+        );
+        assert_eq!(
+            normalize("t = @0 || t = 1970-01-01T08:00:00+08:00 || t = @60"),
+            "t ~ (1970-01-01T00:00:00Z,1970-01-01T08:00:00+08:00,1970-01-01T00:01:00Z)"
+        );
+        // 不同类型的值不合并
+        assert_eq!(normalize("a = 1 || a = 'x'"), "a = 1 || a = 'x'");
+        // && 中的相等比较不合并
+        assert_eq!(normalize("a = 1 && a = 2"), "a = 1 && a = 2");
+    }
+
+    #[test]
+    fn test_dnf_cnf() {
+        let dnf = |s: &str| parse(s).unwrap().to_dnf().to_string();
+        let cnf = |s: &str| parse(s).unwrap().to_cnf().to_string();
+
+        assert_eq!(
+            dnf("a = 1 && (b = 2 || c = 3)"),
+            "a = 1 && b = 2 || a = 1 && c = 3"
+        );
+        assert_eq!(dnf("!(a = 1 && b = 2)"), "!(a = 1) || !(b = 2)");
+        assert_eq!(
+            dnf("!(a = 1 || b = 2) && c = 3"),
+            "!(a = 1) && !(b = 2) && c = 3"
+        );
+        assert_eq!(
+            cnf("a = 1 || b = 2 && c = 3"),
+            "(a = 1 || b = 2) && (a = 1 || c = 3)"
+        );
+        assert_eq!(
+            cnf("(a = 1 && b = 2) || (a = 1 && c = 3)"),
+            "a = 1 && (a = 1 || c = 3) && (b = 2 || a = 1) && (b = 2 || c = 3)"
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use lrpar::Span;
+use serde_json::Number;
+
+use crate::{Bound, Expr, Operand, ParseError, ParseErrorKind, Value};
+
+// 占位符绑定的参数，值不经过词法分析，避免拼接字符串带来的转义和注入问题
+// Params::new().bind("name", "bill").bind("ids", vec![1, 2])
+#[derive(Clone, Debug, Default)]
+pub struct Params(HashMap<String, Value>);
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind<V: Into<Value>>(mut self, name: &str, value: V) -> Self {
+        self.0
+            .insert(name.trim_start_matches(':').to_string(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Params {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::Text(v.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::Text(v)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Value::Number(v.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Number(v.into())
+    }
+}
+
+impl From<u32> for Value {
+    fn from(v: u32) -> Self {
+        Value::Number(v.into())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(v: u64) -> Self {
+        Value::Number(v.into())
+    }
+}
+
+// NaN 和无穷大无法表示为数字，按 null 处理，只能用于 = 和 !=
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Number::from_f64(v).map_or(Value::Null, Value::Number)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(v: Number) -> Self {
+        Value::Number(v)
+    }
+}
+
+impl From<DateTime<FixedOffset>> for Value {
+    fn from(v: DateTime<FixedOffset>) -> Self {
+        Value::DateTime(v)
+    }
+}
+
+impl From<DateTime<Utc>> for Value {
+    fn from(v: DateTime<Utc>) -> Self {
+        Value::DateTime(v.into())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::List(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+// 占位符所在位置要求的值类型
+#[derive(Clone, Copy)]
+enum Expect {
+    Nullable, // = !=，绑定 null 时改写为 ^ null、^^ null
+    Scalar,   // > < >= <= between
+    Text,     // ! !! contains startswith endswith ieq
+    Number,   // len()、算术运算
+    List,     // ~ ~~ << >> has
+}
+
+impl Expect {
+    fn name(&self) -> &'static str {
+        match self {
+            Expect::Nullable => "text, number, bool, datetime or null",
+            Expect::Scalar => "text, number, bool or datetime",
+            Expect::Text => "text",
+            Expect::Number => "number",
+            Expect::List => "list of text, number, bool, null or datetime",
+        }
+    }
+
+    fn check(&self, value: &Value) -> bool {
+        match self {
+            Expect::Nullable => matches!(value, Value::Null) || Expect::Scalar.check(value),
+            Expect::Scalar => matches!(
+                value,
+                Value::Text(_) | Value::Number(_) | Value::Bool(_) | Value::DateTime(_)
+            ),
+            Expect::Text => matches!(value, Value::Text(_)),
+            Expect::Number => matches!(value, Value::Number(_)),
+            Expect::List => match value {
+                Value::List(items) => items.iter().all(|v| {
+                    matches!(
+                        v,
+                        Value::Text(_)
+                            | Value::Number(_)
+                            | Value::Bool(_)
+                            | Value::Null
+                            | Value::DateTime(_)
+                    )
+                }),
+                _ => false,
+            },
+        }
+    }
+}
+
+fn bind_value(
+    span: Span,
+    value: Value,
+    params: &Params,
+    expect: Expect,
+) -> Result<Value, ParseError> {
+    let name = match value {
+        Value::Param(name) => name,
+        value => return Ok(value),
+    };
+    let found = format!(":{}", name);
+    match params.get(&name) {
+        Some(value) if expect.check(value) => Ok(value.clone()),
+        Some(_) => Err(ParseError::new(ParseErrorKind::InvalidParam, span, &found)
+            .with_expected(vec![expect.name().to_string()])),
+        None => Err(ParseError::new(ParseErrorKind::UnboundParam, span, &found)),
+    }
+}
+
+// 算术运算中的占位符只能是数字，函数参数和比较的两侧可以是任意标量
+fn bind_operand(
+    span: Span,
+    operand: Operand,
+    params: &Params,
+    expect: Expect,
+) -> Result<Operand, ParseError> {
+    let operand = match operand {
+        Operand::Value(value) => Operand::Value(bind_value(span, value, params, expect)?),
+        Operand::Arith { op, lhs, rhs } => Operand::Arith {
+            op,
+            lhs: Box::new(bind_operand(span, *lhs, params, Expect::Number)?),
+            rhs: Box::new(bind_operand(span, *rhs, params, Expect::Number)?),
+        },
+        Operand::Call { name, args } => Operand::Call {
+            name,
+            args: args
+                .into_iter()
+                .map(|arg| bind_operand(span, arg, params, Expect::Scalar))
+                .collect::<Result<_, _>>()?,
+        },
+        operand => operand,
+    };
+    Ok(operand)
+}
+
+fn bind_bound(span: Span, bound: Bound, params: &Params) -> Result<Bound, ParseError> {
+    let bound = match bound {
+        Bound::Inclusive(v) => Bound::Inclusive(bind_value(span, v, params, Expect::Scalar)?),
+        Bound::Exclusive(v) => Bound::Exclusive(bind_value(span, v, params, Expect::Scalar)?),
+    };
+    Ok(bound)
+}
+
+impl Expr {
+    // 将表达式中的占位符替换为参数值，缺少参数或类型不符时返回错误
+    pub fn bind(self, params: &Params) -> Result<Expr, ParseError> {
+        let expr = match self {
+            Expr::And { span, lhs, rhs } => Expr::And {
+                span,
+                lhs: Box::new(lhs.bind(params)?),
+                rhs: Box::new(rhs.bind(params)?),
+            },
+            Expr::Or { span, lhs, rhs } => Expr::Or {
+                span,
+                lhs: Box::new(lhs.bind(params)?),
+                rhs: Box::new(rhs.bind(params)?),
+            },
+            Expr::Not { span, expr } => Expr::Not {
+                span,
+                expr: Box::new(expr.bind(params)?),
+            },
+            // Option 的 None 等绑定为 null 时与 mongodb 的 {a: null} 一致，按空值比较
+            Expr::Eq { span, field, value } => {
+                match bind_value(span, value, params, Expect::Nullable)? {
+                    Value::Null => Expr::IsNull { span, field },
+                    value => Expr::Eq { span, field, value },
+                }
+            }
+            Expr::Ne { span, field, value } => {
+                match bind_value(span, value, params, Expect::Nullable)? {
+                    Value::Null => Expr::IsNotNull { span, field },
+                    value => Expr::Ne { span, field, value },
+                }
+            }
+            Expr::Gt { span, field, value } => Expr::Gt {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Scalar)?,
+            },
+            Expr::Gte { span, field, value } => Expr::Gte {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Scalar)?,
+            },
+            Expr::Lt { span, field, value } => Expr::Lt {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Scalar)?,
+            },
+            Expr::Lte { span, field, value } => Expr::Lte {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Scalar)?,
+            },
+            Expr::Like { span, field, value } => Expr::Like {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Text)?,
+            },
+            Expr::NotLike { span, field, value } => Expr::NotLike {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Text)?,
+            },
+            Expr::In { span, field, value } => Expr::In {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::List)?,
+            },
+            Expr::NotIn { span, field, value } => Expr::NotIn {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::List)?,
+            },
+            Expr::Len {
+                span,
+                field,
+                cmp,
+                value,
+            } => Expr::Len {
+                span,
+                field,
+                cmp,
+                value: bind_value(span, value, params, Expect::Number)?,
+            },
+            Expr::Belong { span, field, value } => Expr::Belong {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::List)?,
+            },
+            Expr::NoBelong { span, field, value } => Expr::NoBelong {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::List)?,
+            },
+            Expr::Compare {
+                span,
+                lhs,
+                cmp,
+                rhs,
+            } => Expr::Compare {
+                span,
+                lhs: bind_operand(span, lhs, params, Expect::Scalar)?,
+                cmp,
+                rhs: bind_operand(span, rhs, params, Expect::Scalar)?,
+            },
+            Expr::Any { span, field, expr } => Expr::Any {
+                span,
+                field,
+                expr: Box::new(expr.bind(params)?),
+            },
+            Expr::All { span, field, expr } => Expr::All {
+                span,
+                field,
+                expr: Box::new(expr.bind(params)?),
+            },
+            Expr::Has { span, field, value } => Expr::Has {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::List)?,
+            },
+            Expr::Between {
+                span,
+                field,
+                lower,
+                upper,
+            } => Expr::Between {
+                span,
+                field,
+                lower: bind_bound(span, lower, params)?,
+                upper: bind_bound(span, upper, params)?,
+            },
+            Expr::Match {
+                span,
+                field,
+                op,
+                value,
+                flags,
+            } => Expr::Match {
+                span,
+                field,
+                op,
+                value: bind_value(span, value, params, Expect::Text)?,
+                flags,
+            },
+            Expr::NotMatch {
+                span,
+                field,
+                value,
+                flags,
+            } => Expr::NotMatch {
+                span,
+                field,
+                value: bind_value(span, value, params, Expect::Text)?,
+                flags,
+            },
+            expr @ (Expr::IsNull { .. } | Expr::IsNotNull { .. }) => expr,
+        };
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{yacc_parse, yacc_parse_with, Expr, Params, ParseErrorKind, Value};
+
+    #[test]
+    fn test_bind() {
+        let params = Params::new()
+            .bind("name", "it's \"quoted\" && a=1")
+            .bind("ids", vec![1, 2, 3])
+            .bind("size", 2);
+
+        match yacc_parse_with("name = :name && id ~ :ids && len(tags) > :size", &params) {
+            Ok(Expr::And { lhs, rhs, .. }) => {
+                match *lhs {
+                    Expr::Eq {
+                        value: Value::Text(v),
+                        ..
+                    } => assert_eq!(v, "it's \"quoted\" && a=1"),
+                    rs => panic!("unexpected result {:#?}", rs),
+                }
+                println!("{:#?}", rhs);
+            }
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        // 未绑定时保留占位符
+        match yacc_parse("name = :name") {
+            Ok(Expr::Eq {
+                value: Value::Param(name),
+                ..
+            }) => assert_eq!(name, "name"),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+
+        let params = Params::new().bind("flags", vec![Value::Bool(true), Value::Null]);
+        match yacc_parse_with("flag ~ :flags", &params) {
+            Ok(Expr::In {
+                value: Value::List(items),
+                ..
+            }) => assert!(matches!(items[..], [Value::Bool(true), Value::Null])),
+            rs => panic!("unexpected result {:#?}", rs),
+        };
+    }
+
+    #[test]
+    fn test_bind_null() {
+        // None 和 NaN 绑定为 null，= 和 != 改写为 ^ null 和 ^^ null
+        let params = Params::new()
+            .bind("none", None::<String>)
+            .bind("some", Some(3))
+            .bind("nan", f64::NAN);
+        for (s, printed) in [
+            ("a = :none", "a ^ null"),
+            ("a != :none", "a ^^ null"),
+            ("a = :some", "a = 3"),
+            ("a = :nan && b <> :none", "a ^ null && b ^^ null"),
+        ] {
+            match yacc_parse_with(s, &params) {
+                Ok(expr) => assert_eq!(expr.to_string(), printed, "{}", s),
+                Err(e) => panic!("{}: {}", s, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_bind_error() {
+        match yacc_parse_with("a = 1 && name = :name", &Params::new()) {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::UnboundParam);
+                assert_eq!(e.found, ":name");
+                assert_eq!(
+                    e.render(),
+                    "a = 1 && name = :name\n         ^^^^^^^^^^^^ unbound parameter `:name`"
+                );
+            }
+        };
+
+        // null 只能用于 = 和 !=
+        let params = Params::new().bind("n", None::<i32>);
+        match yacc_parse_with("a > :n", &params) {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => assert_eq!(e.kind, ParseErrorKind::InvalidParam),
+        };
+
+        let params = Params::new().bind("ids", "1,2");
+        match yacc_parse_with("id ~ :ids", &params) {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => assert_eq!(e.kind, ParseErrorKind::InvalidParam),
+        };
+    }
+}
@@ -0,0 +1,280 @@
+use lrpar::Span;
+
+use crate::{Expr, ParseError, ParseErrorKind};
+
+// 字段访问策略，用于限制调用方可以过滤、排序和返回的字段
+// 模式按 . 分段，* 匹配一段，** 匹配任意多段，匹配上级字段时子字段同样匹配
+// FieldPolicy::deny(["password_hash", "*.tenant_id"])
+// FieldPolicy::allow(["name", "age", "addr.*"])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldPolicy {
+    Allow(Vec<String>), // 只允许匹配的字段
+    Deny(Vec<String>),  // 拒绝匹配的字段
+}
+
+fn patterns<I, S>(patterns: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    patterns.into_iter().map(Into::into).collect()
+}
+
+// 模式的每一段依次匹配路径，模式结束时路径剩余部分视为子字段
+fn matches(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, _) => true,
+        (Some((&"**", rest)), _) => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        (Some(_), None) => false,
+        (Some((p, rest)), Some((seg, path))) => (*p == "*" || p == seg) && matches(rest, path),
+    }
+}
+
+impl FieldPolicy {
+    pub fn allow<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        FieldPolicy::Allow(patterns(fields))
+    }
+
+    pub fn deny<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        FieldPolicy::Deny(patterns(fields))
+    }
+
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let path: Vec<&str> = path.split('.').collect();
+        let matched = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|p| matches(&p.split('.').collect::<Vec<_>>(), &path))
+        };
+        match self {
+            FieldPolicy::Allow(patterns) => matched(patterns),
+            FieldPolicy::Deny(patterns) => !matched(patterns),
+        }
+    }
+
+    // 不允许时返回 ForbiddenField，found 为字段的完整路径
+    pub fn check(&self, span: Span, path: &str) -> Result<(), ParseError> {
+        match self.is_allowed(path) {
+            true => Ok(()),
+            false => Err(ParseError::new(ParseErrorKind::ForbiddenField, span, path)),
+        }
+    }
+}
+
+// any()/all() 中的字段相对于数组元素，检查时使用完整路径
+fn join(prefix: &str, field: &str) -> String {
+    match prefix {
+        "" => field.to_string(),
+        prefix => format!("{}.{}", prefix, field),
+    }
+}
+
+impl FieldPolicy {
+    fn check_expr(&self, expr: &Expr, prefix: &str) -> Result<(), ParseError> {
+        match expr {
+            Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+                self.check_expr(lhs, prefix)?;
+                self.check_expr(rhs, prefix)
+            }
+            Expr::Not { expr, .. } => self.check_expr(expr, prefix),
+            Expr::Any { span, field, expr } | Expr::All { span, field, expr } => {
+                let path = join(prefix, field);
+                self.check(*span, &path)?;
+                self.check_expr(expr, &path)
+            }
+            // 条件项引用的字段，包括比较右侧的字段和算术、函数中的字段
+            term => term
+                .fields()
+                .iter()
+                .try_for_each(|field| self.check(term.span(), &join(prefix, field))),
+        }
+    }
+
+    // 去掉引用了不允许字段的条件项，&& 或 || 的一侧被去掉时保留另一侧
+    // ! 内去掉条件项会扩大范围，因此 ! 内引用了不允许的字段时返回 ForbiddenField
+    fn strip_expr(&self, expr: Expr, prefix: &str) -> Result<Option<Expr>, ParseError> {
+        Ok(match expr {
+            Expr::And { span, lhs, rhs } => {
+                match (
+                    self.strip_expr(*lhs, prefix)?,
+                    self.strip_expr(*rhs, prefix)?,
+                ) {
+                    (Some(lhs), Some(rhs)) => Some(Expr::And {
+                        span,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    }),
+                    (lhs, rhs) => lhs.or(rhs),
+                }
+            }
+            Expr::Or { span, lhs, rhs } => {
+                match (
+                    self.strip_expr(*lhs, prefix)?,
+                    self.strip_expr(*rhs, prefix)?,
+                ) {
+                    (Some(lhs), Some(rhs)) => Some(Expr::Or {
+                        span,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    }),
+                    (lhs, rhs) => lhs.or(rhs),
+                }
+            }
+            Expr::Not { span, expr } => {
+                self.check_expr(&expr, prefix)?;
+                Some(Expr::Not { span, expr })
+            }
+            Expr::Any { span, field, expr } => {
+                let path = join(prefix, &field);
+                match self.is_allowed(&path) {
+                    true => self.strip_expr(*expr, &path)?.map(|expr| Expr::Any {
+                        span,
+                        field,
+                        expr: Box::new(expr),
+                    }),
+                    false => None,
+                }
+            }
+            Expr::All { span, field, expr } => {
+                let path = join(prefix, &field);
+                match self.is_allowed(&path) {
+                    true => self.strip_expr(*expr, &path)?.map(|expr| Expr::All {
+                        span,
+                        field,
+                        expr: Box::new(expr),
+                    }),
+                    false => None,
+                }
+            }
+            term => match term
+                .fields()
+                .iter()
+                .all(|field| self.is_allowed(&join(prefix, field)))
+            {
+                true => Some(term),
+                false => None,
+            },
+        })
+    }
+}
+
+impl Expr {
+    // 引用了不允许的字段时返回 ForbiddenField，span 为该条件项
+    pub fn check_fields(&self, policy: &FieldPolicy) -> Result<(), ParseError> {
+        policy.check_expr(self, "")
+    }
+
+    // 去掉引用了不允许字段的条件项，去掉后条件的范围可能变化
+    // ! 内引用了不允许的字段或全部条件项都被去掉时返回 ForbiddenField
+    pub fn strip_fields(self, policy: &FieldPolicy) -> Result<Expr, ParseError> {
+        match policy.strip_expr(self.clone(), "")? {
+            Some(expr) => Ok(expr),
+            None => Err(self
+                .check_fields(policy)
+                .expect_err("stripped term must be forbidden")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FieldPolicy;
+    use crate::{yacc_parse as parse, ParseErrorKind};
+
+    #[test]
+    fn test_policy() {
+        let policy = FieldPolicy::deny(["password_hash", "*.tenant_id", "secret.**.key"]);
+        for (path, allowed) in [
+            ("name", true),
+            ("password_hash", false),
+            ("password_hash.x", false),
+            ("tenant_id", true),
+            ("org.tenant_id", false),
+            ("org.tenant_id.x", false),
+            ("a.b.tenant_id", true),
+            ("secret.key", false),
+            ("secret.a.b.key", false),
+            ("secret.a", true),
+        ] {
+            assert_eq!(policy.is_allowed(path), allowed, "{}", path);
+        }
+
+        let policy = FieldPolicy::allow(["name", "items.*.n", "addr.*"]);
+        for (path, allowed) in [
+            ("name", true),
+            ("name.first", true),
+            ("age", false),
+            ("addr", false),
+            ("addr.city", true),
+            ("items.0.n", true),
+            ("items.0.m", false),
+        ] {
+            assert_eq!(policy.is_allowed(path), allowed, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let policy = FieldPolicy::deny(["password_hash", "items.secret"]);
+        assert!(parse("name = 'a' && any(items, n > 1)")
+            .unwrap()
+            .check_fields(&policy)
+            .is_ok());
+
+        for (s, found) in [
+            ("name = 'a' || password_hash ! '^a'", "password_hash"),
+            ("name = password_hash", "password_hash"),
+            ("lower(password_hash) = 'a'", "password_hash"),
+            ("len(password_hash) > 1", "password_hash"),
+            ("any(items, secret = 1)", "items.secret"),
+        ] {
+            let e = parse(s).unwrap().check_fields(&policy).unwrap_err();
+            assert_eq!(e.kind, ParseErrorKind::ForbiddenField, "{}", s);
+            assert_eq!(e.found, found, "{}", s);
+        }
+
+        let s = "a = 1 && password_hash = 'x'";
+        let e = parse(s)
+            .unwrap()
+            .check_fields(&policy)
+            .unwrap_err()
+            .with_input(s);
+        assert_eq!(
+            e.render(),
+            "a = 1 && password_hash = 'x'\n         ^^^^^^^^^^^^^^^^^^^ forbidden field `password_hash`"
+        );
+    }
+
+    #[test]
+    fn test_strip_fields() {
+        let policy = FieldPolicy::deny(["password_hash", "items.secret"]);
+        for (s, stripped) in [
+            (
+                "a = 1 && password_hash = 'x' && b = 2",
+                Some("a = 1 && b = 2"),
+            ),
+            ("a = 1 || !(b = 2)", Some("a = 1 || !(b = 2)")),
+            ("any(items, n > 1 && secret = 1)", Some("any(items, n > 1)")),
+            ("password_hash = 'x'", None),
+            ("any(items, secret = 1)", None),
+            ("a = 1 || !(password_hash = 'x')", None),
+            ("!(password_hash = 'x' && a = 1)", None),
+        ] {
+            match parse(s).unwrap().strip_fields(&policy) {
+                Ok(expr) => assert_eq!(Some(expr.to_string()).as_deref(), stripped, "{}", s),
+                Err(e) => {
+                    assert_eq!(stripped, None, "{}", s);
+                    assert_eq!(e.kind, ParseErrorKind::ForbiddenField, "{}", s);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use lrpar::Span;
+
+use crate::{Expr, ParseError, ParseErrorKind};
+
+// order by 的一项，desc 为 false 时升序
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderBy {
+    pub field: String,
+    pub desc: bool,
+}
+
+// 完整的查询: 条件及排序、分页和返回字段
+// age > 3 order by name asc, age desc limit 20 offset 40 select name,age
+#[derive(Clone, Debug)]
+pub struct Query {
+    pub expr: Option<Expr>, // 省略条件时为 None
+    pub sorts: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub fields: Vec<String>, // select 的字段，为空时返回全部字段
+    pub(crate) span: Span,   // 子句在原文中的区间，没有子句时为空区间
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            expr: None,
+            sorts: vec![],
+            limit: None,
+            offset: None,
+            fields: vec![],
+            span: Span::new(0, 0),
+        }
+    }
+}
+
+impl Query {
+    // 只允许条件，出现子句时按语法错误处理，found 为子句的关键字
+    pub(crate) fn into_expr(self, input: &str) -> Result<Expr, ParseError> {
+        if self.span.start() < self.span.end() {
+            let found = input[self.span.start()..self.span.end()]
+                .split_whitespace()
+                .next()
+                .unwrap_or_default();
+            let end = self.span.start() + found.len();
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                Span::new(self.span.start(), end),
+                found,
+            ));
+        }
+        self.expr
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Empty, Span::new(0, input.len()), ""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{yacc_parse, yacc_parse_query, yacc_parse_query_with, OrderBy, Params};
+    use crate::{ParseErrorKind, Value};
+
+    #[test]
+    fn test_query() {
+        let s = "age > 3 order by name asc, age DESC limit 20 offset 40 select name,age";
+        let query = yacc_parse_query(s).unwrap();
+        assert_eq!(query.expr.as_ref().unwrap().to_string(), "age > 3");
+        assert_eq!(
+            query.sorts,
+            vec![
+                OrderBy {
+                    field: "name".to_string(),
+                    desc: false
+                },
+                OrderBy {
+                    field: "age".to_string(),
+                    desc: true
+                },
+            ]
+        );
+        assert_eq!((query.limit, query.offset), (Some(20), Some(40)));
+        assert_eq!(query.fields, ["name", "age"]);
+        assert_eq!(
+            query.to_string(),
+            "age > 3 order by name asc, age desc limit 20 offset 40 select name,age"
+        );
+
+        // 子句都可以省略，条件也可以省略
+        let query = yacc_parse_query("a = 1 && b ! 'x' i limit 5").unwrap();
+        assert_eq!(query.expr.unwrap().to_string(), "a = 1 && b ! 'x' i");
+        assert_eq!((query.limit, query.offset), (Some(5), None));
+        let query = yacc_parse_query("ORDER BY a select a.b").unwrap();
+        assert!(query.expr.is_none());
+        assert_eq!(query.fields, ["a.b"]);
+        assert!(yacc_parse_query("").unwrap().expr.is_none());
+
+        // 关键字作为字段名
+        let query = yacc_parse_query("limit > 1 && select = 'x' order by offset limit 1").unwrap();
+        assert_eq!(query.expr.unwrap().to_string(), "limit > 1 && select = 'x'");
+        assert_eq!(query.sorts[0].field, "offset");
+
+        let query =
+            yacc_parse_query_with("a ~ :ids limit 2", &Params::new().bind("ids", vec![1, 2]))
+                .unwrap();
+        assert!(matches!(
+            query.expr,
+            Some(crate::Expr::In {
+                value: Value::List(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_query_error() {
+        for (s, kind, found) in [
+            ("a = 1 limit 1.5", ParseErrorKind::InvalidNumber, "1.5"),
+            ("a = 1 limit -1", ParseErrorKind::InvalidNumber, "-1"),
+            ("a = 1 order by a up", ParseErrorKind::Syntax, "up"),
+            ("a = 1 select a limit 1", ParseErrorKind::Syntax, "limit"),
+        ] {
+            match yacc_parse_query(s) {
+                Ok(rs) => panic!("unexpected result {:#?}", rs),
+                Err(e) => {
+                    assert_eq!(e.kind, kind, "{}", s);
+                    assert_eq!(e.found, found, "{}", s);
+                }
+            }
+        }
+
+        // yacc_parse 只接受条件
+        let s = "a = 1 order by a";
+        let e = yacc_parse(s).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::Syntax);
+        assert_eq!(
+            e.render(),
+            "a = 1 order by a\n      ^^^^^ unexpected `order`"
+        );
+    }
+}
@@ -0,0 +1,561 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use lrpar::Span;
+use serde_json::Number;
+
+use crate::{Bound, Expr, Operand, ParseError, ParseErrorKind, Value};
+
+// 字段声明的类型
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    Text,
+    Number,
+    Bool,
+    DateTime,
+    Array(Box<FieldType>),
+    Object, // 嵌套字段需要单独声明，例如 addr 和 addr.city
+    Any,    // 不检查类型，子字段也不检查
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Text => write!(f, "text"),
+            FieldType::Number => write!(f, "number"),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::DateTime => write!(f, "datetime"),
+            FieldType::Array(t) => write!(f, "array of {}", t),
+            FieldType::Object => write!(f, "object"),
+            FieldType::Any => write!(f, "any"),
+        }
+    }
+}
+
+impl FieldType {
+    // 数组按元素类型比较，与 mongodb 的数组匹配一致
+    fn scalar(&self) -> &FieldType {
+        match self {
+            FieldType::Array(t) => t.scalar(),
+            t => t,
+        }
+    }
+
+    fn accepts(&self, value: &Value) -> bool {
+        match (self.scalar(), value) {
+            (FieldType::Any, _) | (_, Value::Null | Value::Param(_) | Value::Field(_)) => true,
+            (t, Value::List(items)) => items.iter().all(|v| t.accepts(v)),
+            (FieldType::Text, Value::Text(_)) => true,
+            (FieldType::Number, Value::Number(_)) => true,
+            (FieldType::Bool, Value::Bool(_)) => true,
+            (FieldType::DateTime, Value::DateTime(_)) => true,
+            // 兼容以字符串表示的时间，例如 'ISODate(2021-01-01 00:00:00)'
+            (FieldType::DateTime, Value::Text(s)) => {
+                s.starts_with("ISODate(") || datetime_of(s).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    // 将字面量转换为声明的类型，无法转换时返回 None
+    fn coerce(&self, value: Value) -> Option<Value> {
+        let t = self.scalar();
+        if t.accepts(&value) && !matches!((t, &value), (FieldType::DateTime, Value::Text(_))) {
+            return Some(value);
+        }
+        match (t, value) {
+            (t, Value::List(items)) => items
+                .into_iter()
+                .map(|v| t.coerce(v))
+                .collect::<Option<_>>()
+                .map(Value::List),
+            (FieldType::Number, Value::Text(s)) => {
+                s.trim().parse::<Number>().ok().map(Value::Number)
+            }
+            (FieldType::Text, Value::Number(n)) => Some(Value::Text(n.to_string())),
+            (FieldType::Text, Value::Bool(b)) => Some(Value::Text(b.to_string())),
+            (FieldType::Text, Value::DateTime(dt)) => Some(Value::Text(dt.to_rfc3339())),
+            (FieldType::Bool, Value::Text(s)) => s.trim().parse::<bool>().ok().map(Value::Bool),
+            (FieldType::DateTime, Value::Text(s)) if s.starts_with("ISODate(") => {
+                Some(Value::Text(s))
+            }
+            (FieldType::DateTime, Value::Text(s)) => datetime_of(&s),
+            _ => None,
+        }
+    }
+}
+
+fn datetime_of(s: &str) -> Option<Value> {
+    crate::parse_datetime(s.trim(), Span::new(0, 0)).ok()
+}
+
+// 字段名到类型的映射，嵌套字段使用 . 分隔
+// Schema::new().field("name", FieldType::Text).field("tags", FieldType::Array(Box::new(FieldType::Text)))
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schema(BTreeMap<String, FieldType>);
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, name: &str, ty: FieldType) -> Self {
+        self.0.insert(name.to_string(), ty);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // 未声明的字段返回 None，上级字段为 Any 时子字段都是 Any
+    pub fn get(&self, path: &str) -> Option<FieldType> {
+        if let Some(t) = self.0.get(path) {
+            return Some(t.clone());
+        }
+        let mut prefix = path;
+        while let Some(i) = prefix.rfind('.') {
+            prefix = &prefix[..i];
+            if let Some(t) = self.0.get(prefix) {
+                return match t.scalar() {
+                    FieldType::Any => Some(FieldType::Any),
+                    _ => None,
+                };
+            }
+        }
+        None
+    }
+
+    // any()/all() 中的字段相对于数组元素
+    fn element(&self, field: &str) -> Schema {
+        if self.0.get("*") == Some(&FieldType::Any) {
+            return self.clone();
+        }
+        let prefix = format!("{}.", field);
+        match self.get(field) {
+            Some(t) if *t.scalar() == FieldType::Any => Schema::new().field("*", FieldType::Any),
+            _ => Schema(
+                self.0
+                    .iter()
+                    .filter_map(|(k, t)| Some((k.strip_prefix(&prefix)?.to_string(), t.clone())))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn lookup(&self, span: Span, field: &str) -> Result<FieldType, ParseError> {
+        if self.0.get("*") == Some(&FieldType::Any) {
+            return Ok(FieldType::Any);
+        }
+        self.get(field)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::UnknownField, span, field))
+    }
+}
+
+struct Checker<'a> {
+    schema: &'a Schema,
+    coerce: bool,
+}
+
+impl Checker<'_> {
+    fn mismatch(span: Span, field: &str, ty: &FieldType) -> ParseError {
+        ParseError::new(ParseErrorKind::TypeMismatch, span, field)
+            .with_expected(vec![ty.scalar().to_string()])
+    }
+
+    fn invalid(span: Span, field: &str, op: &str) -> ParseError {
+        ParseError::new(ParseErrorKind::InvalidOperator, span, field)
+            .with_expected(vec![op.to_string()])
+    }
+
+    // 字段与字面量比较
+    fn value(&self, span: Span, field: &str, value: Value) -> Result<Value, ParseError> {
+        let ty = self.schema.lookup(span, field)?;
+        if let Value::Field(other) = &value {
+            let other_ty = self.schema.lookup(span, other)?;
+            let (l, r) = (ty.scalar(), other_ty.scalar());
+            if *l != FieldType::Any && *r != FieldType::Any && l != r {
+                return Err(Self::mismatch(span, other, &ty));
+            }
+            return Ok(value);
+        }
+        if self.coerce {
+            return ty
+                .coerce(value)
+                .ok_or_else(|| Self::mismatch(span, field, &ty));
+        }
+        match ty.accepts(&value) {
+            true => Ok(value),
+            false => Err(Self::mismatch(span, field, &ty)),
+        }
+    }
+
+    // 正则等文本匹配只能用于文本字段
+    fn text(&self, span: Span, field: &str, op: &str) -> Result<(), ParseError> {
+        match self.schema.lookup(span, field)?.scalar() {
+            FieldType::Text | FieldType::Any => Ok(()),
+            _ => Err(Self::invalid(span, field, op)),
+        }
+    }
+
+    fn array(&self, span: Span, field: &str, op: &str) -> Result<(), ParseError> {
+        match self.schema.lookup(span, field)? {
+            FieldType::Array(_) | FieldType::Any => Ok(()),
+            _ => Err(Self::invalid(span, field, op)),
+        }
+    }
+
+    fn sized(&self, span: Span, field: &str) -> Result<(), ParseError> {
+        match self.schema.lookup(span, field)? {
+            FieldType::Text | FieldType::Array(_) | FieldType::Any => Ok(()),
+            _ => Err(Self::invalid(span, field, "len()")),
+        }
+    }
+
+    fn bound(&self, span: Span, field: &str, bound: Bound) -> Result<Bound, ParseError> {
+        let bound = match bound {
+            Bound::Inclusive(v) => Bound::Inclusive(self.value(span, field, v)?),
+            Bound::Exclusive(v) => Bound::Exclusive(self.value(span, field, v)?),
+        };
+        Ok(bound)
+    }
+
+    fn operand(&self, span: Span, operand: &Operand, numeric: bool) -> Result<(), ParseError> {
+        match operand {
+            Operand::Field(field) => {
+                let ty = self.schema.lookup(span, field)?;
+                match ty.scalar() {
+                    FieldType::Number | FieldType::Any => Ok(()),
+                    _ if numeric => Err(Self::invalid(span, field, "arithmetic")),
+                    _ => Ok(()),
+                }
+            }
+            Operand::Len(field) => self.sized(span, field),
+            Operand::Value(_) => Ok(()),
+            Operand::Arith { lhs, rhs, .. } => {
+                self.operand(span, lhs, true)?;
+                self.operand(span, rhs, true)
+            }
+            Operand::Call { name, args } => {
+                for arg in args {
+                    match (name.as_str(), arg) {
+                        ("lower" | "upper" | "trim", Operand::Field(field)) => {
+                            self.text(span, field, &format!("{}()", name))?
+                        }
+                        ("abs", arg) => self.operand(span, arg, true)?,
+                        ("size", Operand::Field(field)) => self.array(span, field, "size()")?,
+                        (_, arg) => self.operand(span, arg, false)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn check(&self, expr: Expr) -> Result<Expr, ParseError> {
+        let expr = match expr {
+            Expr::And { span, lhs, rhs } => Expr::And {
+                span,
+                lhs: Box::new(self.check(*lhs)?),
+                rhs: Box::new(self.check(*rhs)?),
+            },
+            Expr::Or { span, lhs, rhs } => Expr::Or {
+                span,
+                lhs: Box::new(self.check(*lhs)?),
+                rhs: Box::new(self.check(*rhs)?),
+            },
+            Expr::Not { span, expr } => Expr::Not {
+                span,
+                expr: Box::new(self.check(*expr)?),
+            },
+            Expr::Eq { span, field, value } => Expr::Eq {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::Ne { span, field, value } => Expr::Ne {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::Gt { span, field, value } => Expr::Gt {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::Gte { span, field, value } => Expr::Gte {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::Lt { span, field, value } => Expr::Lt {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::Lte { span, field, value } => Expr::Lte {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::In { span, field, value } => Expr::In {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::NotIn { span, field, value } => Expr::NotIn {
+                span,
+                value: self.value(span, &field, value)?,
+                field,
+            },
+            Expr::Like { span, field, value } => {
+                self.text(span, &field, "!")?;
+                Expr::Like { span, field, value }
+            }
+            Expr::NotLike { span, field, value } => {
+                self.text(span, &field, "!!")?;
+                Expr::NotLike { span, field, value }
+            }
+            Expr::Match {
+                span,
+                field,
+                op,
+                value,
+                flags,
+            } => {
+                self.text(span, &field, &op.to_string())?;
+                Expr::Match {
+                    span,
+                    field,
+                    op,
+                    value,
+                    flags,
+                }
+            }
+            Expr::NotMatch {
+                span,
+                field,
+                value,
+                flags,
+            } => {
+                self.text(span, &field, "!!")?;
+                Expr::NotMatch {
+                    span,
+                    field,
+                    value,
+                    flags,
+                }
+            }
+            Expr::Len {
+                span,
+                field,
+                cmp,
+                value,
+            } => {
+                self.sized(span, &field)?;
+                Expr::Len {
+                    span,
+                    field,
+                    cmp,
+                    value,
+                }
+            }
+            Expr::Belong { span, field, value } => {
+                self.array(span, &field, "<<")?;
+                Expr::Belong {
+                    span,
+                    value: self.value(span, &field, value)?,
+                    field,
+                }
+            }
+            Expr::NoBelong { span, field, value } => {
+                self.array(span, &field, ">>")?;
+                Expr::NoBelong {
+                    span,
+                    value: self.value(span, &field, value)?,
+                    field,
+                }
+            }
+            Expr::Has { span, field, value } => {
+                self.array(span, &field, "has")?;
+                Expr::Has {
+                    span,
+                    value: self.value(span, &field, value)?,
+                    field,
+                }
+            }
+            Expr::Any { span, field, expr } => {
+                self.array(span, &field, "any()")?;
+                let schema = self.schema.element(&field);
+                let inner = Checker {
+                    schema: &schema,
+                    coerce: self.coerce,
+                };
+                Expr::Any {
+                    span,
+                    field,
+                    expr: Box::new(inner.check(*expr)?),
+                }
+            }
+            Expr::All { span, field, expr } => {
+                self.array(span, &field, "all()")?;
+                let schema = self.schema.element(&field);
+                let inner = Checker {
+                    schema: &schema,
+                    coerce: self.coerce,
+                };
+                Expr::All {
+                    span,
+                    field,
+                    expr: Box::new(inner.check(*expr)?),
+                }
+            }
+            Expr::Between {
+                span,
+                field,
+                lower,
+                upper,
+            } => Expr::Between {
+                span,
+                lower: self.bound(span, &field, lower)?,
+                upper: self.bound(span, &field, upper)?,
+                field,
+            },
+            Expr::Compare {
+                span,
+                lhs,
+                cmp,
+                rhs,
+            } => {
+                self.operand(span, &lhs, false)?;
+                self.operand(span, &rhs, false)?;
+                Expr::Compare {
+                    span,
+                    lhs,
+                    cmp,
+                    rhs,
+                }
+            }
+            Expr::IsNull { span, field } => {
+                self.schema.lookup(span, &field)?;
+                Expr::IsNull { span, field }
+            }
+            Expr::IsNotNull { span, field } => {
+                self.schema.lookup(span, &field)?;
+                Expr::IsNotNull { span, field }
+            }
+        };
+        Ok(expr)
+    }
+}
+
+impl Expr {
+    // 按 schema 检查字段是否存在、字面量类型以及操作符是否适用于字段类型
+    pub fn check(&self, schema: &Schema) -> Result<(), ParseError> {
+        let checker = Checker {
+            schema,
+            coerce: false,
+        };
+        checker.check(self.clone()).map(|_| ())
+    }
+
+    // 同 check，但将字面量转换为字段声明的类型，例如数字字段上的 a = '1' 转换为 a = 1
+    pub fn coerce(self, schema: &Schema) -> Result<Expr, ParseError> {
+        let checker = Checker {
+            schema,
+            coerce: true,
+        };
+        checker.check(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{yacc_parse as parse, Expr, FieldType, ParseErrorKind, Schema, Value};
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("name", FieldType::Text)
+            .field("age", FieldType::Number)
+            .field("ok", FieldType::Bool)
+            .field("t", FieldType::DateTime)
+            .field("tags", FieldType::Array(Box::new(FieldType::Text)))
+            .field("items", FieldType::Array(Box::new(FieldType::Object)))
+            .field("items.n", FieldType::Number)
+            .field("addr", FieldType::Object)
+            .field("addr.city", FieldType::Text)
+            .field("o", FieldType::Any)
+    }
+
+    #[test]
+    fn test_check() {
+        let schema = schema();
+        for s in [
+            "name = 'a' && age > 1 && ok = true && t < 2022-06-13T00:00:00Z",
+            "tags ~ ('a', 'b') && tags has 'x' && len(tags) > 1 && len(name) = 3",
+            "any(items, n > 1) && addr.city contains 'x' && o.x.y = 1 && age between [1, 2)",
+            "age + 1 > age && lower(name) = 'bill' && size(tags) > 0 && age > age",
+            "t > 'ISODate(2021-01-01 00:00:00)' && name ^ null",
+        ] {
+            if let Err(e) = parse(s).unwrap().check(&schema) {
+                panic!("{}: {}", s, e);
+            }
+        }
+
+        let errors = [
+            ("nope = 1", ParseErrorKind::UnknownField, "nope"),
+            ("addr.zip = 1", ParseErrorKind::UnknownField, "addr.zip"),
+            ("age = '1'", ParseErrorKind::TypeMismatch, "age"),
+            ("tags ~ ('a', 1)", ParseErrorKind::TypeMismatch, "tags"),
+            ("t > 'yesterday'", ParseErrorKind::TypeMismatch, "t"),
+            ("any(items, n = 'x')", ParseErrorKind::TypeMismatch, "n"),
+            ("name > age", ParseErrorKind::TypeMismatch, "age"),
+            ("len(age) > 1", ParseErrorKind::InvalidOperator, "age"),
+            ("age ! '^1'", ParseErrorKind::InvalidOperator, "age"),
+            ("name + 1 > 2", ParseErrorKind::InvalidOperator, "name"),
+            ("age has 1", ParseErrorKind::InvalidOperator, "age"),
+        ];
+        for (s, kind, found) in errors {
+            match parse(s).unwrap().check(&schema) {
+                Ok(_) => panic!("expected error for {}", s),
+                Err(e) => {
+                    assert_eq!(e.kind, kind, "{}", s);
+                    assert_eq!(e.found, found, "{}", s);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_coerce() {
+        let schema = schema();
+        match parse("age = '18' && name ~ (1, 2.5) && t > '2022-06-13 00:00:00Z'")
+            .unwrap()
+            .coerce(&schema)
+        {
+            Ok(expr) => assert_eq!(
+                expr.to_string(),
+                "age = 18 && name ~ ('1','2.5') && t > 2022-06-13T00:00:00Z"
+            ),
+            Err(e) => panic!("{}", e),
+        }
+
+        assert!(matches!(
+            parse("ok = 'true'").unwrap().coerce(&schema),
+            Ok(Expr::Eq {
+                value: Value::Bool(true),
+                ..
+            })
+        ));
+        match parse("age = 'abc'").unwrap().coerce(&schema) {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => {
+                assert_eq!(e.kind, ParseErrorKind::TypeMismatch);
+                assert_eq!(
+                    e.to_string(),
+                    "type mismatch for `age`, expected number at 0..11"
+                );
+            }
+        }
+    }
+}
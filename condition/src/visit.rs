@@ -0,0 +1,473 @@
+use crate::{Bound, Expr, Operand, Value};
+
+// 遍历表达式，默认访问所有子表达式、字段、值和操作数，只需重写关心的方法
+// 重写 visit_expr 后可调用 walk_expr 继续访问子节点
+// any()/all() 中的字段相对于数组元素，visit_field 收到的是原样的字段名
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_field(&mut self, _field: &str) {}
+
+    fn visit_value(&mut self, value: &Value) {
+        walk_value(self, value)
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) {
+        walk_operand(self, operand)
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    match expr {
+        Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+        }
+        Expr::Not { expr, .. } => v.visit_expr(expr),
+        Expr::Eq { field, value, .. }
+        | Expr::Ne { field, value, .. }
+        | Expr::Gt { field, value, .. }
+        | Expr::Gte { field, value, .. }
+        | Expr::Lt { field, value, .. }
+        | Expr::Lte { field, value, .. }
+        | Expr::Like { field, value, .. }
+        | Expr::NotLike { field, value, .. }
+        | Expr::In { field, value, .. }
+        | Expr::NotIn { field, value, .. }
+        | Expr::Len { field, value, .. }
+        | Expr::Belong { field, value, .. }
+        | Expr::NoBelong { field, value, .. }
+        | Expr::Has { field, value, .. }
+        | Expr::Match { field, value, .. }
+        | Expr::NotMatch { field, value, .. } => {
+            v.visit_field(field);
+            v.visit_value(value);
+        }
+        Expr::IsNull { field, .. } | Expr::IsNotNull { field, .. } => v.visit_field(field),
+        Expr::Compare { lhs, rhs, .. } => {
+            v.visit_operand(lhs);
+            v.visit_operand(rhs);
+        }
+        Expr::Any { field, expr, .. } | Expr::All { field, expr, .. } => {
+            v.visit_field(field);
+            v.visit_expr(expr);
+        }
+        Expr::Between {
+            field,
+            lower,
+            upper,
+            ..
+        } => {
+            v.visit_field(field);
+            v.visit_value(lower.value());
+            v.visit_value(upper.value());
+        }
+    }
+}
+
+// 比较右侧的字段同样作为字段访问: a > b
+pub fn walk_value<V: Visitor + ?Sized>(v: &mut V, value: &Value) {
+    match value {
+        Value::Field(field) | Value::Len(field) => v.visit_field(field),
+        Value::List(items) => items.iter().for_each(|item| v.visit_value(item)),
+        _ => {}
+    }
+}
+
+pub fn walk_operand<V: Visitor + ?Sized>(v: &mut V, operand: &Operand) {
+    match operand {
+        Operand::Field(field) | Operand::Len(field) => v.visit_field(field),
+        Operand::Value(value) => v.visit_value(value),
+        Operand::Call { args, .. } => args.iter().for_each(|arg| v.visit_operand(arg)),
+        Operand::Arith { lhs, rhs, .. } => {
+            v.visit_operand(lhs);
+            v.visit_operand(rhs);
+        }
+    }
+}
+
+// 原地修改表达式，例如重命名字段、替换值
+pub trait VisitorMut {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_field_mut(&mut self, _field: &mut String) {}
+
+    fn visit_value_mut(&mut self, value: &mut Value) {
+        walk_value_mut(self, value)
+    }
+
+    fn visit_operand_mut(&mut self, operand: &mut Operand) {
+        walk_operand_mut(self, operand)
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+            v.visit_expr_mut(lhs);
+            v.visit_expr_mut(rhs);
+        }
+        Expr::Not { expr, .. } => v.visit_expr_mut(expr),
+        Expr::Eq { field, value, .. }
+        | Expr::Ne { field, value, .. }
+        | Expr::Gt { field, value, .. }
+        | Expr::Gte { field, value, .. }
+        | Expr::Lt { field, value, .. }
+        | Expr::Lte { field, value, .. }
+        | Expr::Like { field, value, .. }
+        | Expr::NotLike { field, value, .. }
+        | Expr::In { field, value, .. }
+        | Expr::NotIn { field, value, .. }
+        | Expr::Len { field, value, .. }
+        | Expr::Belong { field, value, .. }
+        | Expr::NoBelong { field, value, .. }
+        | Expr::Has { field, value, .. }
+        | Expr::Match { field, value, .. }
+        | Expr::NotMatch { field, value, .. } => {
+            v.visit_field_mut(field);
+            v.visit_value_mut(value);
+        }
+        Expr::IsNull { field, .. } | Expr::IsNotNull { field, .. } => v.visit_field_mut(field),
+        Expr::Compare { lhs, rhs, .. } => {
+            v.visit_operand_mut(lhs);
+            v.visit_operand_mut(rhs);
+        }
+        Expr::Any { field, expr, .. } | Expr::All { field, expr, .. } => {
+            v.visit_field_mut(field);
+            v.visit_expr_mut(expr);
+        }
+        Expr::Between {
+            field,
+            lower,
+            upper,
+            ..
+        } => {
+            v.visit_field_mut(field);
+            match lower {
+                Bound::Inclusive(value) | Bound::Exclusive(value) => v.visit_value_mut(value),
+            }
+            match upper {
+                Bound::Inclusive(value) | Bound::Exclusive(value) => v.visit_value_mut(value),
+            }
+        }
+    }
+}
+
+pub fn walk_value_mut<V: VisitorMut + ?Sized>(v: &mut V, value: &mut Value) {
+    match value {
+        Value::Field(field) | Value::Len(field) => v.visit_field_mut(field),
+        Value::List(items) => items.iter_mut().for_each(|item| v.visit_value_mut(item)),
+        _ => {}
+    }
+}
+
+pub fn walk_operand_mut<V: VisitorMut + ?Sized>(v: &mut V, operand: &mut Operand) {
+    match operand {
+        Operand::Field(field) | Operand::Len(field) => v.visit_field_mut(field),
+        Operand::Value(value) => v.visit_value_mut(value),
+        Operand::Call { args, .. } => args.iter_mut().for_each(|arg| v.visit_operand_mut(arg)),
+        Operand::Arith { lhs, rhs, .. } => {
+            v.visit_operand_mut(lhs);
+            v.visit_operand_mut(rhs);
+        }
+    }
+}
+
+// 按值重建表达式，fold_expr 可以返回不同种类的表达式，例如将 a = 1 改写为 a ~ (1)
+pub trait Fold {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_fold_expr(self, expr)
+    }
+
+    fn fold_field(&mut self, field: String) -> String {
+        field
+    }
+
+    fn fold_value(&mut self, value: Value) -> Value {
+        walk_fold_value(self, value)
+    }
+
+    fn fold_operand(&mut self, operand: Operand) -> Operand {
+        walk_fold_operand(self, operand)
+    }
+}
+
+pub fn walk_fold_expr<F: Fold + ?Sized>(f: &mut F, expr: Expr) -> Expr {
+    macro_rules! term {
+        ($variant:ident, $span:expr, $field:expr, $value:expr) => {
+            Expr::$variant {
+                span: $span,
+                field: f.fold_field($field),
+                value: f.fold_value($value),
+            }
+        };
+    }
+    match expr {
+        Expr::And { span, lhs, rhs } => Expr::And {
+            span,
+            lhs: Box::new(f.fold_expr(*lhs)),
+            rhs: Box::new(f.fold_expr(*rhs)),
+        },
+        Expr::Or { span, lhs, rhs } => Expr::Or {
+            span,
+            lhs: Box::new(f.fold_expr(*lhs)),
+            rhs: Box::new(f.fold_expr(*rhs)),
+        },
+        Expr::Not { span, expr } => Expr::Not {
+            span,
+            expr: Box::new(f.fold_expr(*expr)),
+        },
+        Expr::Eq { span, field, value } => term!(Eq, span, field, value),
+        Expr::Ne { span, field, value } => term!(Ne, span, field, value),
+        Expr::Gt { span, field, value } => term!(Gt, span, field, value),
+        Expr::Gte { span, field, value } => term!(Gte, span, field, value),
+        Expr::Lt { span, field, value } => term!(Lt, span, field, value),
+        Expr::Lte { span, field, value } => term!(Lte, span, field, value),
+        Expr::Like { span, field, value } => term!(Like, span, field, value),
+        Expr::NotLike { span, field, value } => term!(NotLike, span, field, value),
+        Expr::In { span, field, value } => term!(In, span, field, value),
+        Expr::NotIn { span, field, value } => term!(NotIn, span, field, value),
+        Expr::Belong { span, field, value } => term!(Belong, span, field, value),
+        Expr::NoBelong { span, field, value } => term!(NoBelong, span, field, value),
+        Expr::Has { span, field, value } => term!(Has, span, field, value),
+        Expr::IsNull { span, field } => Expr::IsNull {
+            span,
+            field: f.fold_field(field),
+        },
+        Expr::IsNotNull { span, field } => Expr::IsNotNull {
+            span,
+            field: f.fold_field(field),
+        },
+        Expr::Len {
+            span,
+            field,
+            cmp,
+            value,
+        } => Expr::Len {
+            span,
+            field: f.fold_field(field),
+            cmp,
+            value: f.fold_value(value),
+        },
+        Expr::Compare {
+            span,
+            lhs,
+            cmp,
+            rhs,
+        } => Expr::Compare {
+            span,
+            lhs: f.fold_operand(lhs),
+            cmp,
+            rhs: f.fold_operand(rhs),
+        },
+        Expr::Any { span, field, expr } => Expr::Any {
+            span,
+            field: f.fold_field(field),
+            expr: Box::new(f.fold_expr(*expr)),
+        },
+        Expr::All { span, field, expr } => Expr::All {
+            span,
+            field: f.fold_field(field),
+            expr: Box::new(f.fold_expr(*expr)),
+        },
+        Expr::Between {
+            span,
+            field,
+            lower,
+            upper,
+        } => Expr::Between {
+            span,
+            field: f.fold_field(field),
+            lower: fold_bound(f, lower),
+            upper: fold_bound(f, upper),
+        },
+        Expr::Match {
+            span,
+            field,
+            op,
+            value,
+            flags,
+        } => Expr::Match {
+            span,
+            field: f.fold_field(field),
+            op,
+            value: f.fold_value(value),
+            flags,
+        },
+        Expr::NotMatch {
+            span,
+            field,
+            value,
+            flags,
+        } => Expr::NotMatch {
+            span,
+            field: f.fold_field(field),
+            value: f.fold_value(value),
+            flags,
+        },
+    }
+}
+
+fn fold_bound<F: Fold + ?Sized>(f: &mut F, bound: Bound) -> Bound {
+    match bound {
+        Bound::Inclusive(value) => Bound::Inclusive(f.fold_value(value)),
+        Bound::Exclusive(value) => Bound::Exclusive(f.fold_value(value)),
+    }
+}
+
+pub fn walk_fold_value<F: Fold + ?Sized>(f: &mut F, value: Value) -> Value {
+    match value {
+        Value::Field(field) => Value::Field(f.fold_field(field)),
+        Value::Len(field) => Value::Len(f.fold_field(field)),
+        Value::List(items) => Value::List(items.into_iter().map(|v| f.fold_value(v)).collect()),
+        value => value,
+    }
+}
+
+pub fn walk_fold_operand<F: Fold + ?Sized>(f: &mut F, operand: Operand) -> Operand {
+    match operand {
+        Operand::Field(field) => Operand::Field(f.fold_field(field)),
+        Operand::Len(field) => Operand::Len(f.fold_field(field)),
+        Operand::Value(value) => Operand::Value(f.fold_value(value)),
+        Operand::Call { name, args } => Operand::Call {
+            name,
+            args: args.into_iter().map(|arg| f.fold_operand(arg)).collect(),
+        },
+        Operand::Arith { op, lhs, rhs } => Operand::Arith {
+            op,
+            lhs: Box::new(f.fold_operand(*lhs)),
+            rhs: Box::new(f.fold_operand(*rhs)),
+        },
+    }
+}
+
+// 收集引用的字段，any()/all() 中的字段加上数组字段作为前缀
+struct Fields {
+    prefix: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl Visitor for Fields {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Any { field, expr, .. } | Expr::All { field, expr, .. } => {
+                self.visit_field(field);
+                self.prefix.push(field.clone());
+                self.visit_expr(expr);
+                self.prefix.pop();
+            }
+            expr => walk_expr(self, expr),
+        }
+    }
+
+    fn visit_field(&mut self, field: &str) {
+        let path = self
+            .prefix
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(field))
+            .collect::<Vec<_>>()
+            .join(".");
+        if !self.fields.contains(&path) {
+            self.fields.push(path);
+        }
+    }
+}
+
+struct Rename<F>(F);
+
+impl<F: FnMut(&str) -> Option<String>> VisitorMut for Rename<F> {
+    fn visit_field_mut(&mut self, field: &mut String) {
+        if let Some(name) = (self.0)(field) {
+            *field = name;
+        }
+    }
+}
+
+impl Expr {
+    // 引用的字段按出现顺序去重，any(items, n > 1) 返回 items 和 items.n
+    pub fn fields(&self) -> Vec<String> {
+        let mut v = Fields {
+            prefix: vec![],
+            fields: vec![],
+        };
+        v.visit_expr(self);
+        v.fields
+    }
+
+    // 返回 None 时保留原字段名，any()/all() 中的字段按原样（相对于数组元素）传入
+    // expr.rename_fields(|f| mapping.get(f).cloned())
+    pub fn rename_fields<F: FnMut(&str) -> Option<String>>(&mut self, f: F) {
+        Rename(f).visit_expr_mut(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_fold_expr, Fold, Visitor};
+    use crate::{yacc_parse as parse, Expr, Value};
+
+    #[test]
+    fn test_fields() {
+        let expr = parse(
+            "a = 1 && (b > c || len(d) > 1) && lower(e) = 'x' && price * (1 + tax) > 1 \
+             && any(items, n between [1, 5) && a = 2) && a ^^ null",
+        )
+        .unwrap();
+        assert_eq!(
+            expr.fields(),
+            ["a", "b", "c", "d", "e", "price", "tax", "items", "items.n", "items.a"]
+        );
+    }
+
+    #[test]
+    fn test_rename_fields() {
+        let mut expr = parse("a = 1 && b > a && any(items, a = 2) && lower(a) = 'x'").unwrap();
+        expr.rename_fields(|f| match f {
+            "a" => Some("x".to_string()),
+            "items" => Some("list".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            expr.to_string(),
+            "x = 1 && b > x && any(list, x = 2) && lower(x) = 'x'"
+        );
+    }
+
+    #[test]
+    fn test_visitor() {
+        // 只重写 visit_value，统计列表中的值
+        struct Count(usize);
+        impl Visitor for Count {
+            fn visit_value(&mut self, value: &Value) {
+                if let Value::List(items) = value {
+                    self.0 += items.len();
+                }
+            }
+        }
+        let mut count = Count(0);
+        count.visit_expr(&parse("a ~ (1, 2) && not (b ~~ ('x')) && c = 1").unwrap());
+        assert_eq!(count.0, 3);
+
+        // 将 a = x 改写为 a ~ (x)
+        struct EqToIn;
+        impl Fold for EqToIn {
+            fn fold_expr(&mut self, expr: Expr) -> Expr {
+                match expr {
+                    Expr::Eq { span, field, value } => Expr::In {
+                        span,
+                        field,
+                        value: Value::List(vec![value]),
+                    },
+                    expr => walk_fold_expr(self, expr),
+                }
+            }
+        }
+        let expr = EqToIn.fold_expr(parse("a = 1 && !(b = 'x') && c > 1").unwrap());
+        assert_eq!(expr.to_string(), "a ~ (1) && !(b ~ ('x')) && c > 1");
+    }
+}
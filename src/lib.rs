@@ -3,6 +3,9 @@
 // #![feature(core_intrinsics)]
 #![feature(closure_lifetime_binder)]
 
+// #[metadata] 生成的代码通过 crossgate:: 引用，crate 内部使用时同样可以解析
+extern crate self as crossgate;
+
 // tools lib
 pub mod object;
 pub mod service;
@@ -10,6 +13,13 @@ pub mod utils;
 
 // export parse
 pub use condition::yacc_parse as parse;
+pub use condition::yacc_parse_with as parse_with;
+pub use condition::{
+    field, set_default_limits, Expr, FieldPolicy, FieldType, Fold, Params, ParseError,
+    ParseErrorKind, ParseLimits, Query, Schema, Visitor, VisitorMut,
+};
+pub use condition::{yacc_parse_query as parse_query, yacc_parse_query_with as parse_query_with};
+pub use helper::cond;
 
 #[macro_use]
 pub mod store;
@@ -2,7 +2,70 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use quote::quote;
-use syn::{parse::Parser, parse_macro_input, parse_quote, ItemFn, ItemStruct};
+use syn::{
+    parse::Parser, parse_macro_input, parse_quote, GenericArgument, ItemFn, ItemStruct,
+    PathArguments, Type,
+};
+
+// 字段的 Rust 类型对应的条件类型，无法识别的类型(嵌套结构体、Map 等)不检查
+fn field_type(ty: &Type) -> proc_macro2::TokenStream {
+    let any = quote! { crossgate::FieldType::Any };
+    let path = match ty {
+        Type::Path(p) => &p.path,
+        Type::Reference(r) => return field_type(&r.elem),
+        Type::Array(a) => {
+            let inner = field_type(&a.elem);
+            return quote! { crossgate::FieldType::Array(Box::new(#inner)) };
+        }
+        Type::Slice(s) => {
+            let inner = field_type(&s.elem);
+            return quote! { crossgate::FieldType::Array(Box::new(#inner)) };
+        }
+        _ => return any,
+    };
+    let Some(seg) = path.segments.last() else {
+        return any;
+    };
+    let arg = match &seg.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(t) => Some(t),
+            _ => None,
+        }),
+        _ => None,
+    };
+    match (seg.ident.to_string().as_str(), arg) {
+        ("String" | "str" | "char", _) => quote! { crossgate::FieldType::Text },
+        (
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize" | "f32" | "f64",
+            _,
+        ) => quote! { crossgate::FieldType::Number },
+        ("bool", _) => quote! { crossgate::FieldType::Bool },
+        ("DateTime" | "NaiveDateTime", _) => quote! { crossgate::FieldType::DateTime },
+        ("Option" | "Box", Some(t)) => field_type(t),
+        ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", Some(t)) => {
+            let inner = field_type(t);
+            quote! { crossgate::FieldType::Array(Box::new(#inner)) }
+        }
+        _ => any,
+    }
+}
+
+// 字段名以 #[serde(rename = "...")] 为准
+fn field_name(field: &syn::Field) -> String {
+    let mut name = field.ident.as_ref().unwrap().to_string();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<syn::LitStr>()?.value();
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<proc_macro2::TokenStream>()?;
+            }
+            Ok(())
+        });
+    }
+    name
+}
 
 #[proc_macro_attribute]
 pub fn metadata(mut uid_field_name: TokenStream, input: TokenStream) -> TokenStream {
@@ -45,6 +108,12 @@ pub fn metadata(mut uid_field_name: TokenStream, input: TokenStream) -> TokenStr
         ]);
     }
 
+    let schema = item_struct.fields.iter().map(|field| {
+        let name = field_name(field);
+        let ty = field_type(&field.ty);
+        quote! { .field(#name, #ty) }
+    });
+
     quote! {
         #[derive(Debug,Clone,serde::Deserialize,serde::Serialize,typed_builder::TypedBuilder)]
         #[builder(field_defaults(default))]
@@ -56,9 +125,87 @@ pub fn metadata(mut uid_field_name: TokenStream, input: TokenStream) -> TokenStr
             fn kind(&self) -> &str {&self.kind}
             fn update_uid(&mut self, id: &str) { if self.#uid_field_name.is_empty() {self.#uid_field_name = id.to_string()}}
             fn update_version(&mut self,version:u64) { self.version = version }
+            fn schema() -> crossgate::Schema { crossgate::Schema::new() #(#schema)* }
         }
 
         #kind_fn
     }
     .into()
 }
+
+// 将 {var} 替换为等长的占位符 ":var "，出错位置与原文一致，引号内的内容不替换
+fn interpolate(src: &str) -> Result<(String, Vec<String>), String> {
+    let mut out = String::with_capacity(src.len());
+    let mut vars = vec![];
+    let mut quote = None;
+    let mut chars = src.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                out.push(c);
+                if let Some((_, c)) = chars.next() {
+                    out.push(c);
+                }
+            }
+            (Some(q), c) if c == q => {
+                quote = None;
+                out.push(c);
+            }
+            (Some(_), c) => out.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                out.push(c);
+            }
+            (None, '{') => {
+                let end = src[i..]
+                    .find('}')
+                    .map(|n| i + n)
+                    .ok_or_else(|| "unclosed `{` in condition".to_string())?;
+                let name = &src[i + 1..end];
+                let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    return Err(format!("invalid variable `{{{}}}` in condition", name));
+                }
+                out.push(':');
+                out.push_str(name);
+                out.push(' ');
+                vars.push(name.to_string());
+                // 跳过变量名和 }
+                for _ in 0..=name.len() {
+                    chars.next();
+                }
+            }
+            (None, c) => out.push(c),
+        }
+    }
+    Ok((out, vars))
+}
+
+// 编译期按 cond.y 检查条件语法，{var} 作为参数绑定而不是拼接到文本中
+// let expr: Result<Expr, ParseError> = cond!("age > {age} && name ~ {names}");
+// 用户注册的函数在运行时才知道，编译期不检查函数名
+#[proc_macro]
+pub fn cond(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as syn::LitStr);
+    let src = lit.value();
+
+    let (text, vars) = match interpolate(&src) {
+        Ok(rs) => rs,
+        Err(e) => return syn::Error::new(lit.span(), e).to_compile_error().into(),
+    };
+    match condition::yacc_parse(&text) {
+        Err(e) if e.kind != condition::ParseErrorKind::UnknownFunction => {
+            let msg = format!("invalid condition\n{}", e.with_input(&src).render());
+            return syn::Error::new(lit.span(), msg).to_compile_error().into();
+        }
+        _ => {}
+    }
+
+    let names = vars.iter();
+    let idents = vars.iter().map(|v| Ident::new(v, lit.span()));
+    quote! {
+        crossgate::parse_with(#text, &crossgate::Params::new() #(.bind(#names, #idents.clone()))*)
+    }
+    .into()
+}
@@ -2,4 +2,6 @@
 fn tests() {
     let t = trybuild::TestCases::new();
     // t.pass("tests/custom.rs");
+    // cond! 在编译期报告条件错误
+    t.compile_fail("tests/ui/cond_*.rs");
 }
@@ -0,0 +1,3 @@
+fn main() {
+    let _ = crossgate::cond!("age > 3 && name = = 'bill'");
+}
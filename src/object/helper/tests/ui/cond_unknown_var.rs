@@ -0,0 +1,4 @@
+fn main() {
+    let age = 3;
+    let _ = crossgate::cond!("age > {age} && name = {name}");
+}
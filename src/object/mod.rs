@@ -1,5 +1,7 @@
 pub use helper::metadata;
 
+use crate::Schema;
+
 pub trait Object: Clone + Send + Sync + 'static {
     fn uid(&self) -> &str;
     fn version(&self) -> u64;
@@ -7,4 +9,9 @@ pub trait Object: Clone + Send + Sync + 'static {
 
     fn update_uid(&mut self, id: &str);
     fn update_version(&mut self, version: u64);
+
+    // 字段类型，#[metadata] 根据结构体字段生成，用于执行前检查条件
+    fn schema() -> Schema {
+        Schema::new()
+    }
 }
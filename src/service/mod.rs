@@ -30,14 +30,15 @@ where
         }
     }
 
-    fn intercept(&self, q: Condition<F>) -> Condition<F> {
+    fn intercept(&self, q: Condition<F>) -> crate::Result<Condition<F>> {
         let mut q = q;
         q.with_db(&self.schema).with_table(&self.table);
-        q
+        q.check_fields()?;
+        Ok(q)
     }
 
     pub async fn list(&self, q: Condition<F>) -> crate::Result<Vec<T>> {
-        match self.storage.clone().list(self.intercept(q)).await {
+        match self.storage.clone().list(self.intercept(q)?).await {
             Ok(rs) => Ok(rs),
             Err(e) => {
                 if StoreError::DataNotFound.eq(&e) {
@@ -49,7 +50,7 @@ where
     }
 
     pub async fn get(&self, q: Condition<F>) -> crate::Result<Option<T>> {
-        match self.storage.clone().get(self.intercept(q)).await {
+        match self.storage.clone().get(self.intercept(q)?).await {
             Ok(rs) => Ok(Some(rs)),
             Err(e) => {
                 if StoreError::DataNotFound.eq(&e) {
@@ -61,7 +62,7 @@ where
     }
 
     pub async fn apply(&self, t: T, q: Condition<F>) -> crate::Result<Option<T>> {
-        match self.storage.clone().apply(t, self.intercept(q)).await {
+        match self.storage.clone().apply(t, self.intercept(q)?).await {
             Ok(rs) => Ok(Some(rs)),
             Err(e) => {
                 if StoreError::DataNotFound.eq(&e) {
@@ -73,35 +74,35 @@ where
     }
 
     pub async fn update(&self, t: T, q: Condition<F>) -> crate::Result<Option<T>> {
-        match self.storage.clone().update(t, self.intercept(q)).await {
+        match self.storage.clone().update(t, self.intercept(q)?).await {
             Ok(t) => Ok(t),
             Err(e) => return Err(anyhow::anyhow!("{}", e.to_string())),
         }
     }
 
     pub async fn save(&self, t: T, q: Condition<F>) -> crate::Result<Option<T>> {
-        match self.storage.clone().save(t, self.intercept(q)).await {
+        match self.storage.clone().save(t, self.intercept(q)?).await {
             Ok(t) => Ok(t),
             Err(e) => return Err(anyhow::anyhow!("{}", e.to_string())),
         }
     }
 
     pub async fn remove(&self, q: Condition<F>) -> crate::Result<()> {
-        match self.storage.clone().delete(self.intercept(q)).await {
+        match self.storage.clone().delete(self.intercept(q)?).await {
             Ok(_) => Ok(()),
             Err(e) => return Err(anyhow::anyhow!("{}", e.to_string())),
         }
     }
 
     pub async fn count(&self, q: Condition<F>) -> crate::Result<u64> {
-        match self.storage.clone().count(self.intercept(q)).await {
+        match self.storage.clone().count(self.intercept(q)?).await {
             Ok(rs) => Ok(rs),
             Err(e) => return Err(anyhow::anyhow!("{}", e.to_string())),
         }
     }
 
     pub async fn watch(&self, ctx: Context, q: Condition<F>) -> crate::Result<Receiver<Event<T>>> {
-        match self.storage.clone().watch(ctx, self.intercept(q)).await {
+        match self.storage.clone().watch(ctx, self.intercept(q)?).await {
             Ok(t) => Ok(t),
             Err(e) => return Err(anyhow::anyhow!("{}", e.to_string())),
         }
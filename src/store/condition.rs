@@ -21,12 +21,17 @@ pub struct Condition<T: Filter> {
     pub(crate) table: String,
     pub(crate) page: usize,
     pub(crate) size: usize,
+    pub(crate) offset: Option<usize>, // 跳过的条数，设置后不再按 page * size 计算
     pub(crate) sorts: Vec<Sort>,
-    pub(crate) fields: Vec<String>,
+    pub(crate) fields: Vec<String>,     // update、apply 更新的字段
+    pub(crate) projection: Vec<String>, // list、get 返回的字段，为空时返回全部字段
     pub filter: T,
     pub(crate) update_version: bool,
     pub(crate) pageable: bool,
     pub(crate) enable_convert: bool, // 隐含转换，例如 _id => ObjectId
+    pub(crate) schema: Option<(crate::Schema, bool)>, // 字段类型及是否转换字面量
+    pub(crate) limits: Option<crate::ParseLimits>, // 条件的规模限制
+    pub(crate) field_policy: Option<(crate::FieldPolicy, bool)>, // 字段策略及是否去掉不允许的字段
 }
 
 impl<T> Condition<T>
@@ -40,11 +45,16 @@ where
             pageable: false,
             page: 0,
             size: 10,
+            offset: None,
             update_version: true,
             sorts: Default::default(),
             fields: Default::default(),
+            projection: Default::default(),
             filter: t,
             enable_convert: false,
+            schema: None,
+            limits: None,
+            field_policy: None,
         }
     }
     pub fn with_db(&mut self, db: &str) -> &mut Condition<T> {
@@ -71,13 +81,32 @@ where
         self
     }
 
+    // 只返回指定的字段，与 with_fields 指定的更新字段互不影响
+    pub fn with_projection(&mut self, fields: &[&str]) -> &mut Condition<T> {
+        self.projection = fields.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
     pub fn with_page(&mut self, page: usize, size: usize) -> &mut Condition<T> {
         self.page = page;
         self.size = size;
+        self.offset = None;
+        self.pageable = true;
+        self
+    }
+
+    // 按条数分页，offset 不必是 limit 的整数倍
+    pub fn with_limit(&mut self, limit: usize, offset: usize) -> &mut Condition<T> {
+        self.size = limit;
+        self.offset = Some(offset);
         self.pageable = true;
         self
     }
 
+    pub(crate) fn skip(&self) -> usize {
+        self.offset.unwrap_or(self.page * self.size)
+    }
+
     pub fn with_update_version(&mut self, update_version: bool) -> &mut Condition<T> {
         self.update_version = update_version;
         self
@@ -90,8 +119,149 @@ where
         self
     }
 
+    // 设置后 wheres 会按字段类型检查条件，需在 wheres 之前调用
+    // with_schema(Test::schema(), true)
+    pub fn with_schema(&mut self, schema: crate::Schema, coerce: bool) -> &mut Condition<T> {
+        self.schema = Some((schema, coerce));
+        self
+    }
+
+    // 设置后 wheres 会拒绝超出限制的条件，比全局的 set_default_limits（默认不限制）更严格时生效
+    // with_limits(ParseLimits { max_depth: 4, max_list_len: 100, ..Default::default() })
+    pub fn with_limits(&mut self, limits: crate::ParseLimits) -> &mut Condition<T> {
+        self.limits = Some(limits);
+        self
+    }
+
+    // 限制条件、排序和返回字段可以引用的字段，需在 wheres 之前调用
+    // strip 为 false 时返回 ForbiddenField，为 true 时去掉不允许的条件项、排序和返回字段
+    // with_field_policy(FieldPolicy::deny(["password_hash", "*.tenant_id"]), false)
+    pub fn with_field_policy(
+        &mut self,
+        policy: crate::FieldPolicy,
+        strip: bool,
+    ) -> &mut Condition<T> {
+        self.field_policy = Some((policy, strip));
+        self
+    }
+
+    // 按字段策略检查排序、更新字段和返回字段，Service 在执行前调用
+    pub fn check_fields(&mut self) -> anyhow::Result<&mut Self> {
+        let (policy, strip) = match &self.field_policy {
+            Some(field_policy) => field_policy,
+            None => return Ok(self),
+        };
+        if *strip {
+            self.sorts.retain(|sort| policy.is_allowed(&sort.field));
+            self.fields.retain(|field| policy.is_allowed(field));
+            self.projection.retain(|field| policy.is_allowed(field));
+        } else {
+            let span = lrpar::Span::new(0, 0);
+            for field in self
+                .sorts
+                .iter()
+                .map(|sort| &sort.field)
+                .chain(&self.fields)
+                .chain(&self.projection)
+            {
+                policy.check(span, field)?;
+            }
+        }
+        self.filter.check_fields(policy, *strip)?;
+        Ok(self)
+    }
+
+    // 解析完整的查询，同时设置条件、排序、分页和返回字段，省略的部分保持不变
+    // query("age > 3 order by name asc, age desc limit 20 offset 40 select name,age")
+    pub fn query<S: ToString + ?Sized>(&mut self, input: &S) -> anyhow::Result<&mut Self> {
+        self.query_with(input, &crate::Params::new())
+    }
+
+    pub fn query_with<S: ToString + ?Sized>(
+        &mut self,
+        input: &S,
+        params: &crate::Params,
+    ) -> anyhow::Result<&mut Self> {
+        let input = input.to_string();
+        self.check_input(&input)?;
+        let query = condition::yacc_parse_query_with(&input, params)?;
+        if let Some(expr) = query.expr {
+            self.filter.parse_expr(expr)?;
+            self.check()?;
+        }
+        if !query.sorts.is_empty() {
+            let sorts = query
+                .sorts
+                .into_iter()
+                .map(|order| Sort {
+                    field: order.field,
+                    order: match order.desc {
+                        true => SortDirection::Descending,
+                        false => SortDirection::Ascending,
+                    },
+                })
+                .collect();
+            self.with_sort(sorts);
+        }
+        if query.limit.is_some() || query.offset.is_some() {
+            let limit = query.limit.unwrap_or(self.size);
+            self.with_limit(limit, query.offset.unwrap_or(0));
+        }
+        if !query.fields.is_empty() {
+            self.projection = query.fields;
+        }
+        self.check_fields()?;
+        Ok(self)
+    }
+
     pub fn wheres<S: ToString + ?Sized>(&mut self, input: &S) -> anyhow::Result<&mut Self> {
-        self.filter.parse(&input.to_string())?;
+        let input = input.to_string();
+        self.check_input(&input)?;
+        self.filter.parse(&input)?;
+        self.check()?;
+        Ok(self)
+    }
+
+    // 使用代码中构造的表达式
+    // wheres_expr(field("age").gt(3).and(field("name").is_in(["a", "b"])))
+    pub fn wheres_expr(&mut self, expr: crate::Expr) -> anyhow::Result<&mut Self> {
+        self.filter.parse_expr(expr)?;
+        self.check()?;
+        Ok(self)
+    }
+
+    // 在解析之前检查原文长度
+    fn check_input(&self, input: &str) -> anyhow::Result<()> {
+        if let Some(limits) = &self.limits {
+            limits.check_input(input)?;
+        }
+        Ok(())
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if let Some(limits) = &self.limits {
+            self.filter.check_limits(limits)?;
+        }
+        if let Some((policy, strip)) = &self.field_policy {
+            self.filter.check_fields(policy, *strip)?;
+        }
+        match &self.schema {
+            Some((schema, coerce)) => self.filter.check(schema, *coerce),
+            None => Ok(()),
+        }
+    }
+
+    // 参数化条件，值不经过词法分析
+    // wheres_with("name = :name && id ~ :ids", &Params::new().bind("name", name).bind("ids", ids))
+    pub fn wheres_with<S: ToString + ?Sized>(
+        &mut self,
+        input: &S,
+        params: &crate::Params,
+    ) -> anyhow::Result<&mut Self> {
+        let input = input.to_string();
+        self.check_input(&input)?;
+        self.filter.parse_with(&input, params)?;
+        self.check()?;
         Ok(self)
     }
 }
@@ -108,4 +278,264 @@ mod test {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[test]
+    fn test_parse_error() {
+        let mut cond = new_mongo_condition();
+        match cond.wheres("a = 1.2.3") {
+            Ok(_) => panic!("expected parse error"),
+            Err(e) => match e.downcast_ref::<crate::ParseError>() {
+                Some(e) => assert_eq!(e.kind, crate::ParseErrorKind::InvalidNumber),
+                None => panic!("{}", e),
+            },
+        }
+    }
+
+    #[test]
+    fn test_wheres_with() {
+        let mut cond = new_mongo_condition();
+        let params = crate::Params::new()
+            .bind("name", "bill' || a = 1")
+            .bind("ids", vec![1, 2]);
+        match cond.wheres_with("name = :name && id ~ :ids", &params) {
+            Ok(_) => assert_eq!(
+                cond.filter.0,
+                bson::doc! {"$and": [
+                    {"name": {"$eq": "bill' || a = 1"}},
+                    {"id": {"$in": [1_i64, 2_i64]}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        }
+
+        match cond.wheres("name = :name") {
+            Ok(_) => panic!("expected unbound parameter error"),
+            Err(e) => match e.downcast_ref::<crate::ParseError>() {
+                Some(e) => assert_eq!(e.kind, crate::ParseErrorKind::UnboundParam),
+                None => panic!("{}", e),
+            },
+        }
+    }
+
+    #[test]
+    fn test_with_schema() {
+        use crate::object::{metadata, Object};
+
+        #[metadata]
+        struct Test {
+            name: String,
+            age: Option<u8>,
+            #[serde(rename = "labels")]
+            tags: Vec<String>,
+        }
+
+        let schema = Test::schema();
+        assert_eq!(schema.get("age"), Some(crate::FieldType::Number));
+        assert_eq!(
+            schema.get("labels"),
+            Some(crate::FieldType::Array(Box::new(crate::FieldType::Text)))
+        );
+        assert_eq!(schema.get("_id"), Some(crate::FieldType::Text));
+
+        let mut cond = new_mongo_condition();
+        cond.with_schema(schema.clone(), true);
+        match cond.wheres("age = '18' && labels ~ (1)") {
+            Ok(_) => assert_eq!(
+                cond.filter.0,
+                bson::doc! {"$and": [
+                    {"age": {"$eq": 18_i64}},
+                    {"labels": {"$in": ["1"]}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        }
+
+        let mut cond = new_mongo_condition();
+        cond.with_schema(schema, false);
+        for (input, kind) in [
+            ("age = '18'", crate::ParseErrorKind::TypeMismatch),
+            ("tags = 'x'", crate::ParseErrorKind::UnknownField),
+            ("len(age) > 1", crate::ParseErrorKind::InvalidOperator),
+        ] {
+            match cond.wheres(input) {
+                Ok(_) => panic!("expected error for {}", input),
+                Err(e) => match e.downcast_ref::<crate::ParseError>() {
+                    Some(e) => assert_eq!(e.kind, kind),
+                    None => panic!("{}", e),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_cond_macro() {
+        let name = "bill' || a = 1";
+        let ids = vec![1, 2];
+        let expr = crate::cond!("name = {name} && id ~ {ids} && tag = '{x}'").unwrap();
+
+        let mut cond = new_mongo_condition();
+        match cond.wheres_expr(expr) {
+            Ok(_) => assert_eq!(
+                cond.filter.0,
+                bson::doc! {"$and": [
+                    {"name": {"$eq": "bill' || a = 1"}},
+                    {"id": {"$in": [1_i64, 2_i64]}},
+                    {"tag": {"$eq": "{x}"}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        }
+        // 变量在宏之后仍可使用
+        assert_eq!(ids.len(), 2);
+
+        // 参数类型与操作符不符时在运行时返回错误
+        let ids = "1,2";
+        match crate::cond!("id ~ {ids}") {
+            Ok(rs) => panic!("unexpected result {:#?}", rs),
+            Err(e) => assert_eq!(e.kind, crate::ParseErrorKind::InvalidParam),
+        }
+    }
+
+    #[test]
+    fn test_with_limits() {
+        let limits = crate::ParseLimits {
+            max_input_len: 64,
+            max_depth: 2,
+            max_list_len: 3,
+            ..Default::default()
+        };
+        let mut cond = new_mongo_condition();
+        cond.with_limits(limits);
+        assert!(cond.wheres("a = 1 && (b = 2 || c ~ (1, 2, 3))").is_ok());
+
+        let long = format!("a = '{}'", "x".repeat(64));
+        for (input, found) in [
+            (long.as_str(), "input length"),
+            ("a = 1 && (b = 2 || !(c = 3))", "depth"),
+            ("a ~ (1, 2, 3, 4)", "list length"),
+        ] {
+            match cond.wheres(input) {
+                Ok(_) => panic!("expected error for {}", input),
+                Err(e) => match e.downcast_ref::<crate::ParseError>() {
+                    Some(e) => {
+                        assert_eq!(e.kind, crate::ParseErrorKind::LimitExceeded);
+                        assert_eq!(e.found, found);
+                    }
+                    None => panic!("{}", e),
+                },
+            }
+        }
+
+        // 绑定的参数同样受限制
+        let params = crate::Params::new().bind("ids", vec![1, 2, 3, 4]);
+        assert!(cond.wheres_with("a ~ :ids", &params).is_err());
+        assert!(cond
+            .wheres_expr(crate::field("a").is_in([1, 2, 3, 4]))
+            .is_err());
+
+        // 全局默认不限制，ParseLimits::default() 只在 with_limits 时生效
+        let list = format!("a ~ ({})", vec!["1"; 2000].join(","));
+        assert!(new_mongo_condition().wheres(&list).is_ok());
+        let mut cond = new_mongo_condition();
+        cond.with_limits(crate::ParseLimits::default());
+        assert!(cond.wheres(&list).is_err());
+    }
+
+    #[test]
+    fn test_with_field_policy() {
+        use super::{Sort, SortDirection};
+
+        let policy = crate::FieldPolicy::deny(["password_hash", "*.tenant_id"]);
+        let sort = |field: &str| Sort {
+            field: field.to_string(),
+            order: SortDirection::Ascending,
+        };
+
+        let mut cond = new_mongo_condition();
+        cond.with_field_policy(policy.clone(), false);
+        assert!(cond.wheres("name = 'a' && org.name = 'b'").is_ok());
+        for input in ["password_hash = 'x'", "any(orgs, tenant_id = 1)"] {
+            match cond.wheres(input) {
+                Ok(_) => panic!("expected error for {}", input),
+                Err(e) => match e.downcast_ref::<crate::ParseError>() {
+                    Some(e) => assert_eq!(e.kind, crate::ParseErrorKind::ForbiddenField),
+                    None => panic!("{}", e),
+                },
+            }
+        }
+        cond.with_sort(vec![sort("name"), sort("org.tenant_id")]);
+        match cond.check_fields() {
+            Ok(_) => panic!("expected error"),
+            Err(e) => assert_eq!(
+                e.downcast_ref::<crate::ParseError>()
+                    .map(|e| e.found.as_str()),
+                Some("org.tenant_id")
+            ),
+        }
+
+        let mut cond = new_mongo_condition();
+        cond.with_field_policy(policy, true)
+            .with_sort(vec![sort("name"), sort("password_hash")])
+            .with_fields(&["name", "org.tenant_id"])
+            .with_projection(&["password_hash", "age"]);
+        cond.wheres("name = 'a' && password_hash = 'x'").unwrap();
+        assert_eq!(cond.filter.0, bson::doc! {"name": {"$eq": "a"}});
+        cond.check_fields().unwrap();
+        assert_eq!(cond.sorts.len(), 1);
+        assert_eq!(cond.fields, vec!["name".to_string()]);
+        assert_eq!(cond.projection, vec!["age".to_string()]);
+
+        for input in [
+            "password_hash = 'x'",
+            "!(password_hash = 'x' && name = 'a')",
+        ] {
+            match cond.wheres(input) {
+                Ok(_) => panic!("expected error for {}", input),
+                Err(e) => match e.downcast_ref::<crate::ParseError>() {
+                    Some(e) => assert_eq!(e.kind, crate::ParseErrorKind::ForbiddenField),
+                    None => panic!("{}", e),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        use super::SortDirection;
+
+        let mut cond = new_mongo_condition();
+        cond.query("age > 3 order by name asc, age desc limit 20 offset 40 select name,age")
+            .unwrap();
+        assert_eq!(cond.filter.0, bson::doc! {"age": {"$gt": 3_i64}});
+        assert_eq!(
+            cond.sorts
+                .iter()
+                .map(|s| (s.field.as_str(), s.order))
+                .collect::<Vec<_>>(),
+            vec![
+                ("name", SortDirection::Ascending),
+                ("age", SortDirection::Descending)
+            ]
+        );
+        assert_eq!((cond.size, cond.skip(), cond.pageable), (20, 40, true));
+        assert_eq!(cond.projection, vec!["name".to_string(), "age".to_string()]);
+
+        // select 只影响返回字段，不改变更新的字段
+        let mut cond = new_mongo_condition();
+        cond.with_fields(&["age"])
+            .query("a = 1 select name")
+            .unwrap();
+        assert_eq!(cond.fields, vec!["age".to_string()]);
+        assert_eq!(cond.projection, vec!["name".to_string()]);
+
+        // 省略的部分保持不变，字段策略同样适用于排序和返回字段
+        let mut cond = new_mongo_condition();
+        cond.with_page(2, 5)
+            .query_with("a = :a", &crate::Params::new().bind("a", 1))
+            .unwrap();
+        assert_eq!((cond.size, cond.skip()), (5, 10));
+        cond.with_field_policy(crate::FieldPolicy::deny(["secret"]), false);
+        assert!(cond.query("order by secret desc").is_err());
+        assert!(cond.query("a = 1 select name,secret.key").is_err());
+    }
 }
@@ -10,4 +10,13 @@ pub enum StoreError {
     ConnectionError(String),
     #[error("other error `{0}`")]
     OtherError(String),
+    #[error("select `{0}` leaves out fields required by the type: {1}, use the any_type api or #[serde(default)]")]
+    ProjectionError(String, String),
+}
+
+impl StoreError {
+    // select 的字段不包含类型必需的字段时解码失败
+    pub(crate) fn projection(fields: &[String], e: impl std::fmt::Display) -> Self {
+        StoreError::ProjectionError(fields.join(","), e.to_string())
+    }
 }
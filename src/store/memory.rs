@@ -0,0 +1,790 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+
+use bson::oid::ObjectId;
+use futures::Future;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::Receiver;
+
+use super::condition::{Condition, Sort, SortDirection};
+use super::{current_time_sess, Context, Event, Filter, GetFilter, Result, Storage, StoreError};
+use crate::object::Object;
+use crate::utils::dict::{
+    compare_and_merge, from_unstructed_to_type, from_value_to_unstructed, get, value_to_map,
+};
+use crate::utils::{matchs::Predicate, Unstructed};
+
+// 变更通知的容量，watch 消费过慢时会丢弃旧的通知
+const NOTIFY_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone)]
+enum Change {
+    Added(Unstructed),
+    Updated(Unstructed),
+    Deleted(String),
+}
+
+#[derive(Debug, Clone)]
+struct Notify {
+    db: String,
+    table: String,
+    change: Change,
+}
+
+type Table = Vec<(String, Unstructed)>;
+
+// 进程内存储，实现与 MongoStore 相同的语义，条件通过 utils::matchs 求值
+// 主要用于测试以及不依赖数据库的嵌入式场景
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    tables: Arc<RwLock<HashMap<(String, String), Table>>>,
+    notify: broadcast::Sender<Notify>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let (notify, _) = broadcast::channel(NOTIFY_CAPACITY);
+        Self {
+            tables: Default::default(),
+            notify,
+        }
+    }
+
+    // 每次操作只编译一次条件，未设置条件时返回 None
+    fn predicate<F: GetFilter>(filter: &F) -> Result<Option<Predicate>> {
+        filter
+            .get_predicate()
+            .map_err(|e| StoreError::OtherError(e.to_string()))
+    }
+
+    fn is_match(item: &Unstructed, predicate: &Option<Predicate>) -> bool {
+        match predicate {
+            Some(predicate) => predicate.matches(item),
+            None => true,
+        }
+    }
+
+    // save 和 apply 共用的插入，uid 已存在时返回 DuplicateKey
+    fn insert(rows: &mut Table, uid: &str, item: Unstructed) -> Result<()> {
+        if rows.iter().any(|(key, _)| key.eq(uid)) {
+            return Err(StoreError::DuplicateKey(uid.to_string()));
+        }
+        rows.push((uid.to_string(), item));
+        Ok(())
+    }
+
+    fn read<R>(&self, db: &str, table: &str, f: impl FnOnce(&Table) -> R) -> Result<R> {
+        let tables = self
+            .tables
+            .read()
+            .map_err(|e| StoreError::OtherError(e.to_string()))?;
+        match tables.get(&(db.to_string(), table.to_string())) {
+            Some(rows) => Ok(f(rows)),
+            None => Ok(f(&vec![])),
+        }
+    }
+
+    fn write<R>(&self, db: &str, table: &str, f: impl FnOnce(&mut Table) -> R) -> Result<R> {
+        let mut tables = self
+            .tables
+            .write()
+            .map_err(|e| StoreError::OtherError(e.to_string()))?;
+        Ok(f(tables
+            .entry((db.to_string(), table.to_string()))
+            .or_default()))
+    }
+
+    fn select(&self, db: &str, table: &str, expr: &Option<Predicate>) -> Result<Vec<Unstructed>> {
+        self.read(db, table, |rows| {
+            rows.iter()
+                .filter(|(_, item)| Self::is_match(item, expr))
+                .map(|(_, item)| item.clone())
+                .collect()
+        })
+    }
+
+    fn send(&self, db: &str, table: &str, change: Change) {
+        // 没有 watch 订阅时发送会失败，忽略即可
+        let _ = self.notify.send(Notify {
+            db: db.to_string(),
+            table: table.to_string(),
+            change,
+        });
+    }
+}
+
+fn to_unstructed<T: Serialize + DeserializeOwned>(t: &T) -> Result<Unstructed> {
+    from_value_to_unstructed(t).map_err(|e| StoreError::OtherError(e.to_string()))
+}
+
+fn to_type<T: Serialize + DeserializeOwned>(item: Unstructed) -> Result<T> {
+    from_unstructed_to_type(item).map_err(|e| StoreError::OtherError(e.to_string()))
+}
+
+// 按 mongodb 的类型顺序比较: null < number < string < object < array < bool
+fn type_rank(v: &Value) -> u8 {
+    match v {
+        Value::Null => 0,
+        Value::Number(_) => 1,
+        Value::String(_) => 2,
+        Value::Object(_) => 3,
+        Value::Array(_) => 4,
+        Value::Bool(_) => 5,
+    }
+}
+
+fn compare_value(lhs: &Value, rhs: &Value) -> Ordering {
+    match (lhs, rhs) {
+        (Value::Number(l), Value::Number(r)) => l
+            .as_f64()
+            .partial_cmp(&r.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(l), Value::String(r)) => l.cmp(r),
+        (Value::Bool(l), Value::Bool(r)) => l.cmp(r),
+        _ => type_rank(lhs).cmp(&type_rank(rhs)),
+    }
+}
+
+fn sort_items(items: &mut [Unstructed], sorts: &[Sort]) {
+    if sorts.is_empty() {
+        return;
+    }
+    items.sort_by(|lhs, rhs| {
+        for s in sorts {
+            let ordering = compare_value(&lhs.get(&s.field), &rhs.get(&s.field));
+            let ordering = match s.order {
+                SortDirection::Ascending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+}
+
+// 与 mongodb 的 projection 一致: 只返回指定的字段和 _id，缺失的字段不返回
+// 不包含类型必需的字段时返回 ProjectionError
+fn project<T: Serialize + DeserializeOwned>(item: Unstructed, fields: &[String]) -> Result<T> {
+    if fields.is_empty() {
+        return to_type(item);
+    }
+    let mut rs = Unstructed::new();
+    for field in fields.iter().map(String::as_str).chain(["_id"]) {
+        match item.get(field) {
+            Value::Null => {}
+            value => rs.set(field, &value),
+        }
+    }
+    from_unstructed_to_type(rs).map_err(|e| StoreError::projection(fields, e))
+}
+
+// 与 MongoStore 的 update 保持一致: 只更新 fields 中指定的字段
+fn merge_fields<T: Serialize + DeserializeOwned>(
+    item: &mut Unstructed,
+    t: &T,
+    fields: &[String],
+    update_version: bool,
+) -> Result<()> {
+    let map = value_to_map(t).map_err(|e| StoreError::OtherError(e.to_string()))?;
+    for field in fields {
+        item.set(field, &get(&map, field));
+    }
+    if update_version {
+        item.set_value("version", current_time_sess());
+    }
+    Ok(())
+}
+
+impl<T, F> Storage<T, F> for MemoryStore
+where
+    T: Object + DeserializeOwned + Serialize + Unpin + Debug,
+    F: Filter + GetFilter,
+{
+    type ListFuture<'a>
+        = impl Future<Output = Result<Vec<T>>>
+    where
+        Self: 'a;
+    fn list<'r>(self, q: Condition<F>) -> Self::ListFuture<'r> {
+        async move {
+            let skip = q.skip();
+            let Condition {
+                db,
+                table,
+                filter,
+                size: page_size,
+                sorts,
+                pageable,
+                projection,
+                ..
+            } = q;
+
+            let expr = Self::predicate(&filter)?;
+            let mut items = self.select(&db, &table, &expr)?;
+            sort_items(&mut items, &sorts);
+
+            let items: Vec<Unstructed> = if pageable {
+                items.into_iter().skip(skip).take(page_size).collect()
+            } else {
+                items
+            };
+
+            items
+                .into_iter()
+                .map(|item| project(item, &projection))
+                .collect()
+        }
+    }
+
+    type GetFuture<'a>
+        = impl Future<Output = Result<T>>
+    where
+        Self: 'a;
+    fn get<'r>(self, q: Condition<F>) -> Self::GetFuture<'r> {
+        async move {
+            let Condition {
+                db,
+                table,
+                filter,
+                projection,
+                ..
+            } = q;
+
+            let expr = Self::predicate(&filter)?;
+            match self.select(&db, &table, &expr)?.into_iter().next() {
+                Some(item) => project(item, &projection),
+                None => Err(StoreError::DataNotFound),
+            }
+        }
+    }
+
+    type StreamFuture<'a>
+        = impl Future<Output = Result<Receiver<Event<T>>>>
+    where
+        Self: 'a;
+    fn watch<'r>(self, ctx: Context, q: Condition<F>) -> Self::StreamFuture<'r> {
+        let Condition {
+            filter, db, table, ..
+        } = q;
+
+        async move {
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+
+            let expr = Self::predicate(&filter)?;
+            // 先订阅再读取快照，避免两者之间的变更丢失
+            let mut notify = self.notify.subscribe();
+            let items = self.select(&db, &table, &expr)?;
+
+            tokio::spawn(async move {
+                let loop_block = async {
+                    for item in items {
+                        let item = match to_type::<T>(item) {
+                            Ok(item) => item,
+                            Err(e) => {
+                                log::error!("watch find decode: {}", e);
+                                continue;
+                            }
+                        };
+                        if let Err(e) = tx.send(Event::Added(item)).await {
+                            log::error!("watch find send: {}", e);
+                            return;
+                        }
+                    }
+
+                    loop {
+                        let Notify {
+                            db: evt_db,
+                            table: evt_table,
+                            change,
+                        } = match notify.recv().await {
+                            Ok(evt) => evt,
+                            Err(RecvError::Lagged(n)) => {
+                                log::error!("watch memory store lagged {} events", n);
+                                continue;
+                            }
+                            Err(RecvError::Closed) => break,
+                        };
+
+                        if evt_db != db || evt_table != table {
+                            continue;
+                        }
+
+                        let evt = match change {
+                            Change::Added(item) | Change::Updated(item)
+                                if !Self::is_match(&item, &expr) =>
+                            {
+                                continue
+                            }
+                            Change::Added(item) => to_type::<T>(item).map(Event::Added),
+                            Change::Updated(item) => to_type::<T>(item).map(Event::Updated),
+                            Change::Deleted(uid) => {
+                                let mut key = Unstructed::new();
+                                key.set_value("_id", uid);
+                                Ok(Event::Deleted(key))
+                            }
+                        };
+
+                        let evt = match evt {
+                            Ok(evt) => evt,
+                            Err(e) => Event::Error(format!("watch decode error: {}", e)),
+                        };
+
+                        if let Err(e) = tx.send(evt).await {
+                            log::error!("{:?}", e.to_string());
+                            break;
+                        }
+                    }
+                };
+
+                let mut ctx = ctx;
+                tokio::select! {
+                    _ = loop_block => {},
+                    _ = ctx.done() => {},
+                }
+            });
+
+            Ok(rx)
+        }
+    }
+
+    type SaveFuture<'a>
+        = impl Future<Output = Result<Option<T>>>
+    where
+        Self: 'a;
+    fn save<'r>(self, t: T, q: Condition<F>) -> Self::SaveFuture<'r> {
+        let Condition { db, table, .. } = q;
+
+        async move {
+            let mut t = t;
+            t.update_uid(&ObjectId::new().to_string());
+
+            let uid = t.uid().to_string();
+            let item = to_unstructed(&t)?;
+
+            self.write(&db, &table, |rows| Self::insert(rows, &uid, item.clone()))??;
+            self.send(&db, &table, Change::Added(item.clone()));
+
+            Ok(Some(to_type(item)?))
+        }
+    }
+
+    type ApplyFuture<'a>
+        = impl Future<Output = Result<T>>
+    where
+        Self: 'a;
+    fn apply<'r>(self, t: T, q: Condition<F>) -> Self::ApplyFuture<'r> {
+        let Condition {
+            db,
+            table,
+            filter,
+            fields,
+            ..
+        } = q;
+
+        async move {
+            let mut t = t;
+            let expr = Self::predicate(&filter)?;
+
+            let old = self.read(&db, &table, |rows| {
+                rows.iter()
+                    .find(|(_, item)| Self::is_match(item, &expr))
+                    .cloned()
+            })?;
+
+            let (uid, old) = match old {
+                Some((uid, old)) => (uid, to_type::<T>(old)?),
+                None => {
+                    let item = to_unstructed(&t)?;
+                    let uid = t.uid().to_string();
+                    self.write(&db, &table, |rows| Self::insert(rows, &uid, item.clone()))??;
+                    self.send(&db, &table, Change::Added(item));
+                    return Ok(t);
+                }
+            };
+
+            let mut old = old;
+            if let Ok(mut update) = compare_and_merge(&mut old, &mut t, fields) {
+                update.update_version(current_time_sess());
+
+                let item = to_unstructed(&update)?;
+                self.write(&db, &table, |rows| {
+                    if let Some(row) = rows.iter_mut().find(|(key, _)| key.eq(&uid)) {
+                        row.1 = item.clone();
+                    }
+                })?;
+                self.send(&db, &table, Change::Updated(item));
+                return Ok(update);
+            }
+
+            Ok(t)
+        }
+    }
+
+    type RemoveFuture<'a>
+        = impl Future<Output = Result<()>>
+    where
+        Self: 'a;
+    fn delete<'r>(self, q: Condition<F>) -> Self::RemoveFuture<'r> {
+        let Condition {
+            db, table, filter, ..
+        } = q;
+
+        async move {
+            let expr = Self::predicate(&filter)?;
+
+            let removed = self.write(&db, &table, |rows| {
+                let mut removed = vec![];
+                rows.retain(|(uid, item)| {
+                    if Self::is_match(item, &expr) {
+                        removed.push(uid.clone());
+                        return false;
+                    }
+                    true
+                });
+                removed
+            })?;
+
+            for uid in removed {
+                self.send(&db, &table, Change::Deleted(uid));
+            }
+            Ok(())
+        }
+    }
+
+    type CountFuture<'a>
+        = impl Future<Output = Result<u64>>
+    where
+        Self: 'a;
+    fn count<'r>(self, q: Condition<F>) -> Self::CountFuture<'r> {
+        async move {
+            let Condition {
+                db, table, filter, ..
+            } = q;
+
+            let expr = Self::predicate(&filter)?;
+            Ok(self.select(&db, &table, &expr)?.len() as u64)
+        }
+    }
+
+    type UpdateFuture<'a>
+        = impl Future<Output = Result<Option<T>>>
+    where
+        Self: 'a;
+    fn update<'r>(self, t: T, q: Condition<F>) -> Self::UpdateFuture<'r> {
+        let Condition {
+            db,
+            table,
+            filter,
+            fields,
+            update_version,
+            ..
+        } = q;
+
+        async move {
+            let expr = Self::predicate(&filter)?;
+
+            let updated = self.write(&db, &table, |rows| -> Result<Option<Unstructed>> {
+                match rows
+                    .iter_mut()
+                    .find(|(_, item)| Self::is_match(item, &expr))
+                {
+                    Some((_, item)) => {
+                        merge_fields(item, &t, &fields, update_version)?;
+                        Ok(Some(item.clone()))
+                    }
+                    None => Ok(None),
+                }
+            })??;
+
+            if let Some(item) = updated {
+                self.send(&db, &table, Change::Updated(item));
+            }
+
+            // 与 MongoStore 一致，更新后按条件重新查询
+            match self.select(&db, &table, &expr)?.into_iter().next() {
+                Some(item) => Ok(Some(to_type(item)?)),
+                None => Ok(None),
+            }
+        }
+    }
+
+    type UpdateManyFuture<'a>
+        = impl Future<Output = Result<u32>>
+    where
+        Self: 'a;
+    fn update_many<'r>(self, t: T, q: Condition<F>) -> Self::UpdateManyFuture<'r> {
+        let Condition {
+            db,
+            table,
+            filter,
+            fields,
+            update_version,
+            ..
+        } = q;
+
+        async move {
+            let expr = Self::predicate(&filter)?;
+
+            let updated = self.write(&db, &table, |rows| -> Result<Vec<Unstructed>> {
+                let mut updated = vec![];
+                for (_, item) in rows.iter_mut() {
+                    if !Self::is_match(item, &expr) {
+                        continue;
+                    }
+                    let old = item.clone();
+                    merge_fields(item, &t, &fields, update_version)?;
+                    if to_value(&old) != to_value(item) {
+                        updated.push(item.clone());
+                    }
+                }
+                Ok(updated)
+            })??;
+
+            let modified = updated.len() as u32;
+            for item in updated {
+                self.send(&db, &table, Change::Updated(item));
+            }
+            Ok(modified)
+        }
+    }
+}
+
+fn to_value(item: &Unstructed) -> Value {
+    serde_json::to_value(item).unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        object::{metadata, Object},
+        service::Service,
+        store::{new_mongo_condition, MongoFilter, Sort, SortDirection},
+    };
+
+    #[metadata]
+    struct Test {
+        name: String,
+        age: u8,
+    }
+
+    fn service() -> Service<Test, MongoFilter, MemoryStore> {
+        Service::new("test".into(), "test".into(), MemoryStore::new())
+    }
+
+    async fn seed(svc: &Service<Test, MongoFilter, MemoryStore>) {
+        for (name, age) in [("bobo", 3), ("bill", 1), ("alex", 2)] {
+            let t = Test::builder().name(name.into()).age(age).build();
+            svc.save(t, new_mongo_condition()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_get_list() {
+        let svc = service();
+        seed(&svc).await;
+
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'bill'").unwrap();
+        let item = svc.get(q).await.unwrap().unwrap();
+        assert_eq!(item.age, 1);
+
+        let mut q = new_mongo_condition();
+        q.wheres("age >= 2").unwrap();
+        assert_eq!(svc.count(q).await.unwrap(), 2);
+
+        let mut q = new_mongo_condition();
+        q.with_sort(vec![Sort {
+            field: "age".into(),
+            order: SortDirection::Descending,
+        }])
+        .with_page(0, 2);
+        let items = svc.list(q).await.unwrap();
+        assert_eq!(
+            items.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["bobo", "alex"]
+        );
+
+        let mut q = new_mongo_condition();
+        q.query("age >= 1 order by age desc limit 2 offset 1")
+            .unwrap();
+        let items = svc.list(q).await.unwrap();
+        assert_eq!(
+            items.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["alex", "bill"]
+        );
+
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'nobody'").unwrap();
+        assert!(svc.get(q).await.unwrap().is_none());
+    }
+
+    #[metadata]
+    struct Profile {
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        age: u8,
+    }
+
+    #[tokio::test]
+    async fn test_projection() {
+        let svc: Service<Profile, MongoFilter, MemoryStore> =
+            Service::new("test".into(), "profile".into(), MemoryStore::new());
+        let t = Profile::builder().name("bill".into()).age(3).build();
+        let uid = t.uid().to_string();
+        svc.save(t, new_mongo_condition()).await.unwrap();
+
+        let mut q = new_mongo_condition();
+        q.query("name = 'bill' select name").unwrap();
+        let items = svc.list(q).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!((items[0].name.as_str(), items[0].age), ("bill", 0));
+        assert_eq!(items[0].uid(), uid);
+
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'bill'").unwrap().with_projection(&["age"]);
+        let item = svc.get(q).await.unwrap().unwrap();
+        assert_eq!((item.name.as_str(), item.age), ("", 3));
+
+        // 没有 #[serde(default)] 的字段必须在 select 中
+        let store = MemoryStore::new();
+        let svc = Service::new("test".into(), "test".into(), store.clone());
+        seed(&svc).await;
+        let list = |s: &str| {
+            let mut q = new_mongo_condition();
+            q.with_db("test").with_table("test").query(s).unwrap();
+            Storage::<Test, MongoFilter>::list(store.clone(), q)
+        };
+        let items = list("name = 'bill' select name, age").await.unwrap();
+        assert_eq!((items[0].name.as_str(), items[0].age), ("bill", 1));
+        match list("name = 'bill' select name").await {
+            Err(StoreError::ProjectionError(fields, e)) => {
+                assert_eq!(fields, "name");
+                assert!(e.contains("age"), "{}", e);
+            }
+            rs => panic!("{:?}", rs),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete() {
+        let store = MemoryStore::new();
+        let svc = Service::new("test".into(), "test".into(), store.clone());
+        seed(&svc).await;
+
+        let t = Test::builder().name("bill".into()).age(10).build();
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'bill'").unwrap().with_fields(&["age"]);
+        let item = svc.update(t, q).await.unwrap().unwrap();
+        assert_eq!(item.age, 10);
+
+        let t = Test::builder().age(20).build();
+        let mut q = new_mongo_condition();
+        q.with_db("test")
+            .with_table("test")
+            .wheres("age < 5")
+            .unwrap()
+            .with_fields(&["age"]);
+        assert_eq!(
+            Storage::<Test, MongoFilter>::update_many(store, t, q)
+                .await
+                .unwrap(),
+            2
+        );
+
+        let mut q = new_mongo_condition();
+        q.wheres("age >= 20").unwrap();
+        svc.remove(q).await.unwrap();
+        assert_eq!(svc.count(new_mongo_condition()).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        let svc = service();
+
+        let t = Test::builder().name("bobo".into()).age(1).build();
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'bobo'").unwrap().with_fields(&["age"]);
+        svc.apply(t.clone(), q.clone()).await.unwrap();
+        assert_eq!(svc.count(new_mongo_condition()).await.unwrap(), 1);
+
+        let mut t = t;
+        t.age = 2;
+        let item = svc.apply(t, q).await.unwrap().unwrap();
+        assert_eq!(item.age, 2);
+        assert_eq!(svc.count(new_mongo_condition()).await.unwrap(), 1);
+
+        // 条件不匹配时插入，uid 已存在则与 save 一样返回 DuplicateKey
+        let store = MemoryStore::new();
+        let mut q = new_mongo_condition();
+        q.with_db("test").with_table("test");
+        let saved = Storage::<Test, MongoFilter>::save(store.clone(), item, q.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        q.wheres("name = 'alex'").unwrap().with_fields(&["age"]);
+        match Storage::<Test, MongoFilter>::apply(store.clone(), saved, q.clone()).await {
+            Err(StoreError::DuplicateKey(_)) => {}
+            rs => panic!("{:?}", rs),
+        }
+        let mut q = new_mongo_condition();
+        q.with_db("test").with_table("test");
+        assert_eq!(
+            Storage::<Test, MongoFilter>::count(store, q).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let svc = service();
+        seed(&svc).await;
+
+        let (ctx, _h) = Context::new();
+        let mut q = new_mongo_condition();
+        q.wheres("age > 1").unwrap();
+        let mut rx = svc.watch(ctx, q).await.unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(rx.recv().await, Some(Event::Added(_))));
+        }
+
+        // 不满足条件的数据不会被推送
+        let t = Test::builder().name("tom".into()).age(0).build();
+        svc.save(t, new_mongo_condition()).await.unwrap();
+
+        let t = Test::builder().name("jerry".into()).age(5).build();
+        svc.save(t, new_mongo_condition()).await.unwrap();
+        match rx.recv().await {
+            Some(Event::Added(t)) => assert_eq!(t.name, "jerry"),
+            evt => panic!("unexpected event {:?}", evt),
+        }
+
+        let t = Test::builder().age(6).build();
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'jerry'").unwrap().with_fields(&["age"]);
+        svc.update(t, q).await.unwrap();
+        match rx.recv().await {
+            Some(Event::Updated(t)) => assert_eq!(t.age, 6),
+            evt => panic!("unexpected event {:?}", evt),
+        }
+
+        let mut q = new_mongo_condition();
+        q.wheres("name = 'jerry'").unwrap();
+        svc.remove(q).await.unwrap();
+        assert!(matches!(rx.recv().await, Some(Event::Deleted(_))));
+    }
+}
@@ -1,6 +1,9 @@
 mod mongo;
 pub use mongo::*;
 
+mod memory;
+pub use memory::MemoryStore;
+
 mod mongo_extends;
 pub use mongo_extends::{
     MongoDbModel, MongoStorageAggregationExtends, MongoStorageExtends, MongoStorageOpExtends,
@@ -68,7 +71,29 @@ where
 pub trait Filter: Clone + Debug {
     fn enable_convert(&mut self) -> &mut Self;
 
-    fn parse<S: ToString + ?Sized>(&mut self, input: &S) -> anyhow::Result<Box<Self>>;
+    fn parse<S: ToString + ?Sized>(&mut self, input: &S) -> anyhow::Result<Box<Self>> {
+        self.parse_with(input, &crate::Params::new())
+    }
+
+    // 解析后将 :name 占位符替换为 params 中的值
+    fn parse_with<S: ToString + ?Sized>(
+        &mut self,
+        input: &S,
+        params: &crate::Params,
+    ) -> anyhow::Result<Box<Self>>;
+
+    // 使用代码中构造的表达式，不经过字符串解析
+    // parse_expr(field("age").gt(3).and(field("name").is_in(["a", "b"])))
+    fn parse_expr(&mut self, expr: crate::Expr) -> anyhow::Result<Box<Self>>;
+
+    // 按 schema 检查已解析的条件，coerce 为 true 时将字面量转换为字段声明的类型
+    fn check(&mut self, schema: &crate::Schema, coerce: bool) -> anyhow::Result<()>;
+
+    // 检查已解析的条件是否超出限制，例如嵌套层数、列表长度、正则规模
+    fn check_limits(&self, limits: &crate::ParseLimits) -> anyhow::Result<()>;
+
+    // 按字段策略检查已解析的条件，strip 为 true 时去掉引用了不允许字段的条件项
+    fn check_fields(&mut self, policy: &crate::FieldPolicy, strip: bool) -> anyhow::Result<()>;
 }
 
 pub trait Storage<T: Object, F: Filter>: Sync + Send + Clone + 'static {
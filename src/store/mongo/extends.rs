@@ -1,11 +1,9 @@
-use crate::store::mongo::matchs::matchs;
 use crate::store::mongo_extends::MongoStorageOpExtends;
 use crate::store::{current_time_sess, Event};
 use crate::utils::dict::{from_unstructed_to_type, from_value_to_unstructed, get, value_to_map};
 use crate::utils::Unstructed;
 use crate::{store::mongo_extends::MongoStorageAggregationExtends, utils::dict::compare_and_merge};
 use bson::{doc, Bson, Document, Uuid};
-use condition::{yacc_parse as parse, Value};
 use futures::{Future, TryStreamExt};
 use mongodb::action::Action;
 use mongodb::change_stream;
@@ -40,13 +38,14 @@ where
         T: MongoDbModel,
     {
         async move {
+            let skip = q.skip();
             let Condition {
                 db,
                 table,
                 filter,
-                page,
                 size: page_size,
                 fields,
+                projection,
                 sorts,
                 pageable,
                 ..
@@ -57,7 +56,7 @@ where
             let mut opt = FindOptions::builder().build();
 
             if pageable {
-                opt.skip = Some((page * page_size) as u64);
+                opt.skip = Some(skip as u64);
                 opt.limit = Some(page_size as i64);
             }
 
@@ -74,13 +73,11 @@ where
                 opt.sort = Some(doc);
             }
 
-            if fields.len() > 0 {
-                let mut doc = Document::new();
-                for s in fields {
-                    doc.insert(s.clone(), 1);
-                }
-                opt.projection = Some(doc);
-            }
+            // 兼容以前通过 with_fields 指定返回字段
+            opt.projection = match projection.is_empty() {
+                true => super::projection_doc(&fields),
+                false => super::projection_doc(&projection),
+            };
 
             let mut cursor = c
                 .find(filter.get_doc())
@@ -227,6 +224,7 @@ where
                 db,
                 table,
                 fields,
+                projection,
                 filter,
                 ..
             } = q;
@@ -234,13 +232,11 @@ where
 
             let mut opt = FindOneOptions::builder().build();
 
-            if fields.len() > 0 {
-                let mut doc = Document::new();
-                for s in fields {
-                    doc.insert(s.clone(), 1);
-                }
-                opt.projection = Some(doc);
-            }
+            // 兼容以前通过 with_fields 指定返回字段
+            opt.projection = match projection.is_empty() {
+                true => super::projection_doc(&fields),
+                false => super::projection_doc(&projection),
+            };
 
             if let Some(value) = c
                 .find_one(filter.get_doc())
@@ -279,7 +275,11 @@ where
         let block = async move {
             let (tx, rx) = tokio::sync::mpsc::channel(1);
 
-            let (filter_doc, filter_src) = filter.get();
+            // 使用已绑定参数的表达式匹配变更，条件只编译一次
+            let (filter_doc, _) = filter.get();
+            let predicate = filter
+                .get_predicate()
+                .map_err(|e| StoreError::OtherError(e.to_string()))?;
 
             let collection = client.database(&db).collection::<T>(&table);
             let mut cursor = collection
@@ -298,15 +298,14 @@ where
                 .map_err(|e| StoreError::ConnectionError(e.to_string()))?;
 
             let _matchs = move |item: &T| -> bool {
-                if filter_src.eq("") {
-                    return true;
-                }
-                if let Ok(v) = from_value_to_unstructed(item) {
-                    if let Ok(r) = matchs(&mut vec![v], parse(&filter_src).unwrap()) {
-                        return r.len() == 1;
-                    }
+                let predicate = match &predicate {
+                    Some(predicate) => predicate,
+                    None => return true,
+                };
+                match from_value_to_unstructed(item) {
+                    Ok(v) => predicate.matches(&v),
+                    Err(_) => false,
                 }
-                return false;
             };
 
             tokio::spawn(async move {
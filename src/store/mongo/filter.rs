@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use crate::store::Filter;
 use bson::{doc, oid::ObjectId, Document};
-use condition::yacc_parse as parse;
+use condition::{yacc_parse_with as parse_with, Params};
 use mongodb::bson::Bson;
 
 use super::{convert_to_mongodb_time, GetFilter};
@@ -15,14 +15,299 @@ pub enum MongoOp {
     Lte,
     Ne,
     Like,
-    NotLike, // { item: { $not: { $regex: "^p.*" } } }
+    NotLike, // { item: { $type: "string", $not: { $regex: "^p.*" } } }
     In,
     NotIn,
 }
+// 0: 查询文档 1: 条件原文 2: 隐含转换 3: 解析并绑定参数后的表达式，watch 时用于匹配变更
 #[derive(Clone, Debug)]
-pub struct MongoFilter(pub Document, pub String, pub bool);
+pub struct MongoFilter(
+    pub Document,
+    pub String,
+    pub bool,
+    pub Option<condition::Expr>,
+);
 
 impl MongoFilter {
+    fn gen_list(enable_convert: &bool, k: &str, v: &condition::Value) -> anyhow::Result<Vec<Bson>> {
+        let mut str_vec = vec![];
+        let mut object_id_vec = vec![];
+        let mut number_vec = vec![];
+        let mut iosdate_vec = vec![];
+        let mut bool_vec = vec![];
+        // null 可以与其它类型一起出现，匹配值为 null 或缺失的字段
+        let mut null_vec = vec![];
+
+        if let condition::Value::List(vs) = v {
+            for v in vs {
+                match v {
+                    condition::Value::Text(v) => {
+                        log::debug!("filter enable_convert: {}, k: {}", enable_convert, k);
+                        if *enable_convert && k.eq("_id") {
+                            object_id_vec.push(Bson::from(ObjectId::from_str(v.as_str())?));
+                            continue;
+                        }
+
+                        if v.starts_with("ISODate(") && v.ends_with(")") {
+                            // ISODate(2022-06-13 16:00:00) => mongodb: 2022-06-13T16:00:00Z
+                            let new_v = v.replace("ISODate(", "").replace(")", "");
+                            match convert_to_mongodb_time(&new_v) {
+                                Ok(dt) => iosdate_vec.push(Bson::from(dt)),
+                                Err(e) => return Err(anyhow::anyhow!("{:?}", e)),
+                            }
+                            continue;
+                        }
+
+                        str_vec.push(Bson::from(v.as_str()));
+                    }
+                    condition::Value::Number(v) => {
+                        if v.is_f64() {
+                            if let Some(v) = v.as_f64() {
+                                number_vec.push(Bson::from(v));
+                            }
+                        } else if v.is_i64() {
+                            if let Some(v) = v.as_i64() {
+                                number_vec.push(Bson::from(v));
+                            }
+                        } else if v.is_u64() {
+                            if let Some(v) = v.as_u64() {
+                                number_vec.push(Bson::from(v as i64));
+                            }
+                        }
+                    }
+                    condition::Value::DateTime(v) => {
+                        iosdate_vec.push(Bson::DateTime(bson::DateTime::from_chrono(*v)));
+                    }
+                    condition::Value::Bool(v) => bool_vec.push(Bson::Boolean(*v)),
+                    condition::Value::Null => null_vec.push(Bson::Null),
+                    _ => return Err(anyhow::anyhow!("in op unsupport non int or charts")),
+                }
+            }
+        } else {
+            return Err(anyhow::anyhow!("in op just only support list"));
+        }
+
+        if [
+            &str_vec,
+            &number_vec,
+            &object_id_vec,
+            &iosdate_vec,
+            &bool_vec,
+        ]
+        .iter()
+        .filter(|v| !v.is_empty())
+        .count()
+            > 1
+        {
+            return Err(anyhow::anyhow!(
+                "only supports the same type of int or charts in the list"
+            ));
+        }
+
+        let mut list = if !object_id_vec.is_empty() {
+            object_id_vec
+        } else if !str_vec.is_empty() {
+            str_vec
+        } else if !number_vec.is_empty() {
+            number_vec
+        } else if !bool_vec.is_empty() {
+            bool_vec
+        } else {
+            iosdate_vec
+        };
+        list.extend(null_vec);
+        Ok(list)
+    }
+
+    // len(field) 对应 matchs 中的语义: 字符串取字符数，数组取元素个数，对象取键个数，字段不存在时为 0
+    fn gen_len_doc(
+        k: &str,
+        cmp: &condition::Compare,
+        v: &condition::Value,
+    ) -> anyhow::Result<Document> {
+        let len = match v {
+            condition::Value::Number(v) => match v.as_i64() {
+                Some(v) => v,
+                None => return Err(anyhow::anyhow!("len op just only support integer")),
+            },
+            _ => return Err(anyhow::anyhow!("len op just only support number")),
+        };
+
+        let op = Self::compare_op(cmp);
+        let size = Self::gen_size(k);
+
+        Ok(doc! {
+            "$expr": {
+                "$let": {
+                    "vars": { "len": size },
+                    "in": { "$and": [{ "$ne": ["$$len", Bson::Null] }, { op: ["$$len", len] }] },
+                }
+            }
+        })
+    }
+
+    fn compare_op(cmp: &condition::Compare) -> &'static str {
+        match cmp {
+            condition::Compare::Eq => "$eq",
+            condition::Compare::Ne => "$ne",
+            condition::Compare::Gt => "$gt",
+            condition::Compare::Gte => "$gte",
+            condition::Compare::Lt => "$lt",
+            condition::Compare::Lte => "$lte",
+        }
+    }
+
+    // 字符串、数组或对象的长度，字段缺失时为 0，其余类型为 null
+    fn gen_size(k: &str) -> Document {
+        let field = format!("${}", k);
+        doc! {
+            "$switch": {
+                "branches": [
+                    { "case": { "$isArray": &field }, "then": { "$size": &field } },
+                    { "case": { "$eq": [{ "$type": &field }, "string"] }, "then": { "$strLenCP": &field } },
+                    { "case": { "$eq": [{ "$type": &field }, "object"] }, "then": { "$size": { "$objectToArray": &field } } },
+                    { "case": { "$eq": [{ "$type": &field }, "missing"] }, "then": 0 },
+                ],
+                "default": Bson::Null,
+            }
+        }
+    }
+
+    // 操作数对应的聚合表达式，numeric 为 true 时不是数字的字段按 null 处理，
+    // 避免 $add 等操作符报错；除数为 0 时结果为 null，与 matchs 一致
+    fn gen_operand(operand: &condition::Operand, numeric: bool) -> anyhow::Result<Bson> {
+        let expr = match operand {
+            condition::Operand::Field(k) if numeric => {
+                let field = format!("${}", k);
+                doc! {"$cond": [{ "$isNumber": &field }, &field, Bson::Null]}
+            }
+            condition::Operand::Field(k) => return Ok(Bson::from(format!("${}", k))),
+            condition::Operand::Value(v) => {
+                return match v {
+                    condition::Value::Number(v) => match v.as_i64() {
+                        Some(v) => Ok(Bson::from(v)),
+                        None => Ok(v.as_f64().map_or(Bson::Null, Bson::from)),
+                    },
+                    // 以 $ 开头的字符串会被当作字段，使用 $literal
+                    condition::Value::Text(v) if !numeric => {
+                        Ok(Bson::Document(doc! {"$literal": v.as_str()}))
+                    }
+                    condition::Value::Bool(v) if !numeric => Ok(Bson::Boolean(*v)),
+                    condition::Value::DateTime(v) if !numeric => {
+                        Ok(Bson::DateTime(bson::DateTime::from_chrono(*v)))
+                    }
+                    v => Err(anyhow::anyhow!("unsupport operand type {}", v)),
+                };
+            }
+            condition::Operand::Len(k) => Self::gen_size(k),
+            condition::Operand::Arith { op, lhs, rhs } => {
+                let (lhs, rhs) = (Self::gen_operand(lhs, true)?, Self::gen_operand(rhs, true)?);
+                match op {
+                    condition::ArithOp::Add => doc! {"$add": [lhs, rhs]},
+                    condition::ArithOp::Sub => doc! {"$subtract": [lhs, rhs]},
+                    condition::ArithOp::Mul => doc! {"$multiply": [lhs, rhs]},
+                    condition::ArithOp::Div => {
+                        doc! {"$cond": [{ "$eq": [&rhs, 0] }, Bson::Null, { "$divide": [lhs, rhs] }]}
+                    }
+                    condition::ArithOp::Rem => {
+                        doc! {"$cond": [{ "$eq": [&rhs, 0] }, Bson::Null, { "$mod": [lhs, rhs] }]}
+                    }
+                }
+            }
+            condition::Operand::Call { name, args } => return Self::gen_call(name, args),
+        };
+        Ok(Bson::Document(expr))
+    }
+
+    // 内置函数，参数类型不符时结果为 null；用户函数使用注册的转换
+    fn gen_call(name: &str, args: &[condition::Operand]) -> anyhow::Result<Bson> {
+        let string = |arg: Bson, expr: Document| {
+            doc! {"$cond": [{ "$eq": [{ "$type": &arg }, "string"] }, expr, Bson::Null]}
+        };
+        let expr = match (name, args) {
+            ("exists", [condition::Operand::Field(k)]) => {
+                doc! {"$ne": [{ "$type": format!("${}", k) }, "missing"]}
+            }
+            ("now", []) => return Ok(Bson::from("$$NOW")),
+            ("abs", [arg]) => doc! {"$abs": Self::gen_operand(arg, true)?},
+            ("lower", [arg]) => {
+                let arg = Self::gen_operand(arg, false)?;
+                string(arg.clone(), doc! {"$toLower": arg})
+            }
+            ("upper", [arg]) => {
+                let arg = Self::gen_operand(arg, false)?;
+                string(arg.clone(), doc! {"$toUpper": arg})
+            }
+            ("trim", [arg]) => {
+                let arg = Self::gen_operand(arg, false)?;
+                string(arg.clone(), doc! {"$trim": {"input": arg}})
+            }
+            ("size", [arg]) => {
+                let arg = Self::gen_operand(arg, false)?;
+                doc! {"$cond": [{ "$isArray": &arg }, { "$size": &arg }, Bson::Null]}
+            }
+            ("type", [arg]) => {
+                let arg = Self::gen_operand(arg, false)?;
+                doc! {"$switch": {
+                    "branches": [{ "case": { "$isNumber": &arg }, "then": "number" }],
+                    "default": { "$type": &arg },
+                }}
+            }
+            ("date_trunc", [unit, arg]) => {
+                let (unit, arg) = (
+                    Self::gen_operand(unit, false)?,
+                    Self::gen_operand(arg, false)?,
+                );
+                doc! {"$cond": [
+                    { "$eq": [{ "$type": &arg }, "date"] },
+                    { "$dateTrunc": { "date": &arg, "unit": unit } },
+                    Bson::Null,
+                ]}
+            }
+            (name, _) if condition::function::is_builtin(name) => {
+                return Err(anyhow::anyhow!("invalid arguments for function `{}`", name))
+            }
+            (name, args) => {
+                let f = crate::utils::Function::get_mongo(name)?;
+                let args = args
+                    .iter()
+                    .map(|arg| Self::gen_operand(arg, false))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                return f(args);
+            }
+        };
+        Ok(Bson::Document(expr))
+    }
+
+    // price * qty >= 100 => {$expr: {$let: {vars: {lhs: ..., rhs: 100}, in: {$and: [...]}}}}
+    // 两侧都不为 null 且同为数字或类型相同时才比较，与 matchs 一致
+    fn gen_arith_doc(
+        lhs: &condition::Operand,
+        cmp: &condition::Compare,
+        rhs: &condition::Operand,
+    ) -> anyhow::Result<Document> {
+        let op = Self::compare_op(cmp);
+        Ok(doc! {
+            "$expr": {
+                "$let": {
+                    "vars": {
+                        "lhs": Self::gen_operand(lhs, false)?,
+                        "rhs": Self::gen_operand(rhs, false)?,
+                    },
+                    "in": { "$and": [
+                        { "$ne": ["$$lhs", Bson::Null] },
+                        { "$ne": ["$$rhs", Bson::Null] },
+                        { "$or": [
+                            { "$and": [{ "$isNumber": "$$lhs" }, { "$isNumber": "$$rhs" }] },
+                            { "$eq": [{ "$type": "$$lhs" }, { "$type": "$$rhs" }] },
+                        ] },
+                        { op: ["$$lhs", "$$rhs"] },
+                    ] },
+                }
+            }
+        })
+    }
+
     fn gen_doc(
         enable_convert: &bool,
         k: &str,
@@ -41,82 +326,24 @@ impl MongoFilter {
             MongoOp::NotIn => "$nin",
 
             MongoOp::NotLike => {
-                //{ item: { $not: { $regex: "^p.*" } } }
-                return Err(anyhow::anyhow!("notlike not supported"));
+                // 与 matchs 一致，字段缺失或不是字符串时不匹配
+                if let condition::Value::Text(s) = v {
+                    return Ok(doc! {k:doc! {"$type":"string","$not":doc! {"$regex":s.as_str()}}});
+                }
+                return Err(anyhow::anyhow!("not like op just only support text"));
             }
         };
 
+        if let condition::Value::Field(other) = v {
+            return Self::gen_field_doc(k, op, other);
+        }
+
         let mut doc = doc! {};
 
+        // 空列表保留为 $in: []，不匹配任何数据，与 matchs 一致
         if op == "$in" || op == "$nin" {
-            let mut str_vec = vec![];
-            let mut object_id_vec = vec![];
-            let mut number_vec = vec![];
-            let mut iosdate_vec = vec![];
-
-            if let condition::Value::List(vs) = v {
-                for v in vs {
-                    match v {
-                        condition::Value::Text(v) => {
-                            log::debug!("filter enable_convert: {}, k: {}", enable_convert, k);
-                            if *enable_convert && k.eq("_id") {
-                                object_id_vec.push(ObjectId::from_str(v.as_str())?);
-                                continue;
-                            }
-
-                            if v.starts_with("ISODate(") && v.ends_with(")") {
-                                // ISODate(2022-06-13 16:00:00) => mongodb: 2022-06-13T16:00:00Z
-                                let new_v = v.replace("ISODate(", "").replace(")", "");
-                                match convert_to_mongodb_time(&new_v) {
-                                    Ok(dt) => iosdate_vec.push(Bson::from(dt)),
-                                    Err(e) => return Err(anyhow::anyhow!("{:?}", e)),
-                                }
-                                continue;
-                            }
-
-                            str_vec.push(v.as_str().to_string());
-                        }
-                        condition::Value::Number(v) => {
-                            if v.is_f64() {
-                                if let Some(v) = v.as_f64() {
-                                    number_vec.push(Bson::from(v));
-                                }
-                            } else if v.is_i64() {
-                                if let Some(v) = v.as_i64() {
-                                    number_vec.push(Bson::from(v));
-                                }
-                            } else if v.is_u64() {
-                                if let Some(v) = v.as_u64() {
-                                    number_vec.push(Bson::from(v as i64));
-                                }
-                            }
-                        }
-                        _ => return Err(anyhow::anyhow!("in op unsupport non int or charts")),
-                    }
-                }
-            } else {
-                return Err(anyhow::anyhow!("in op just only support list"));
-            }
-
-            if (str_vec.len() > 0 && number_vec.len() > 0)
-                || (str_vec.len() > 0 && object_id_vec.len() > 0)
-                || (number_vec.len() > 0 && object_id_vec.len() > 0)
-            {
-                return Err(anyhow::anyhow!(
-                    "only supports the same type of int or charts in the list"
-                ));
-            }
-
-            if object_id_vec.len() > 0 {
-                doc.insert(k, doc! {op:object_id_vec});
-            } else if str_vec.len() > 0 {
-                doc.insert(k, doc! {op:str_vec});
-            } else if number_vec.len() > 0 {
-                doc.insert(k, doc! {op:number_vec});
-            } else if iosdate_vec.len() > 0 {
-                doc.insert(k, doc! {op:iosdate_vec});
-            }
-
+            let list = Self::gen_list(enable_convert, k, v)?;
+            doc.insert(k, doc! {op:list});
             return Ok(doc);
         }
 
@@ -166,26 +393,89 @@ impl MongoFilter {
                 doc! {k:doc!{op:value}}
             }
             condition::Value::Bool(v) => doc! {k:doc!{op:Bson::Boolean(*v)}},
+            condition::Value::DateTime(v) => {
+                doc! {k:doc!{op:Bson::DateTime(bson::DateTime::from_chrono(*v))}}
+            }
             _ => return Err(anyhow::anyhow!("unsupport type parse")),
         };
 
         Ok(doc)
     }
 
+    // 字段间比较 a > b => {$expr: {$and: [{$gt: ["$a", "$b"]}, {$gt: ["$a", null]}, {$gt: ["$b", null]}]}}
+    // $expr 中缺失字段按 null 参与比较，两侧字段都存在且不为 null 时才比较，与 matchs 一致
+    fn gen_field_doc(k: &str, op: &str, other: &str) -> anyhow::Result<Document> {
+        if !["$eq", "$ne", "$gt", "$gte", "$lt", "$lte"].contains(&op) {
+            return Err(anyhow::anyhow!(
+                "field reference only supports comparison op, got {}",
+                op
+            ));
+        }
+        let (lhs, rhs) = (format!("${}", k), format!("${}", other));
+        Ok(doc! {"$expr": {"$and": [
+            {op: [&lhs, &rhs]},
+            {"$gt": [&lhs, Bson::Null]},
+            {"$gt": [&rhs, Bson::Null]},
+        ]}})
+    }
+
+    // $elemMatch 中不能使用 $expr，字段间比较、算术运算和函数无法用于数组元素
+    fn gen_elem_doc(docs: Vec<Document>) -> anyhow::Result<Document> {
+        fn has_expr(v: &Bson) -> bool {
+            match v {
+                Bson::Document(doc) => doc.iter().any(|(k, v)| k == "$expr" || has_expr(v)),
+                Bson::Array(items) => items.iter().any(has_expr),
+                _ => false,
+            }
+        }
+        let inner: Document = docs.into_iter().flatten().collect();
+        if has_expr(&Bson::Document(inner.clone())) {
+            return Err(anyhow::anyhow!(
+                "any/all only support field compare with value, got {}",
+                inner
+            ));
+        }
+        Ok(inner)
+    }
+
+    // 单字段的操作符文档使用 {k: {$not: {...}}}，其余情况使用 {$nor: [...]}
+    // {a: {$gt: 1}} => {a: {$not: {$gt: 1}}}
+    // {$and: [...]} => {$nor: [{$and: [...]}]}
+    fn gen_not_doc(inner: Document) -> Document {
+        if inner.len() == 1 {
+            if let Some((k, Bson::Document(ops))) = inner.iter().next() {
+                if !k.starts_with('$')
+                    && !ops.is_empty()
+                    && ops.keys().all(|op| op.starts_with('$') && op != "$not")
+                {
+                    return doc! {k: {"$not": ops.clone()}};
+                }
+            }
+        }
+        doc! {"$nor": [inner]}
+    }
+
     fn eval(&self, exprs: &[condition::Expr]) -> anyhow::Result<Vec<bson::Document>> {
         let mut docs = vec![];
         for expr in exprs.into_iter() {
             match expr {
-                condition::Expr::And { span: _, lhs, rhs } => {
+                // 连续的 && / || 合并为一个 $and / $or 数组
+                condition::Expr::And { .. } => {
+                    let terms: Vec<_> = expr.conjuncts().into_iter().cloned().collect();
                     let mut doc = doc! {};
-                    doc.insert("$and", self.eval(&[*lhs.clone(), *rhs.clone()])?);
+                    doc.insert("$and", self.eval(&terms)?);
                     docs.push(doc);
                 }
-                condition::Expr::Or { span: _, lhs, rhs } => {
+                condition::Expr::Or { .. } => {
+                    let terms: Vec<_> = expr.disjuncts().into_iter().cloned().collect();
                     let mut doc = doc! {};
-                    doc.insert("$or", self.eval(&[*lhs.clone(), *rhs.clone()])?);
+                    doc.insert("$or", self.eval(&terms)?);
                     docs.push(doc);
                 }
+                condition::Expr::Not { span: _, expr } => {
+                    let inner = self.eval(&[*expr.clone()])?.into_iter().flatten().collect();
+                    docs.push(Self::gen_not_doc(inner));
+                }
                 condition::Expr::Eq {
                     span: _,
                     field,
@@ -271,8 +561,131 @@ impl MongoFilter {
                         MongoOp::NotIn,
                     )?);
                 }
-
-                _ => return Err(anyhow::anyhow!("not support op")),
+                condition::Expr::IsNull { span: _, field } => {
+                    docs.push(doc! {field.as_str():Bson::Null});
+                }
+                condition::Expr::IsNotNull { span: _, field } => {
+                    docs.push(doc! {field.as_str():doc! {"$ne":Bson::Null}});
+                }
+                condition::Expr::Len {
+                    span: _,
+                    field,
+                    cmp,
+                    value,
+                } => {
+                    docs.push(Self::gen_len_doc(field.as_str(), cmp, value)?);
+                }
+                condition::Expr::Belong {
+                    span: _,
+                    field,
+                    value,
+                } => {
+                    // 数组非空且所有元素都属于列表
+                    let list = Self::gen_list(&self.2, field.as_str(), value)?;
+                    docs.push(doc! {"$and": [
+                        {format!("{}.0", field):doc! {"$exists":true}},
+                        {field.as_str():doc! {"$not":doc! {"$elemMatch":doc! {"$nin":list}}}},
+                    ]});
+                }
+                condition::Expr::NoBelong {
+                    span: _,
+                    field,
+                    value,
+                } => {
+                    // 数组中存在不属于列表的元素
+                    let list = Self::gen_list(&self.2, field.as_str(), value)?;
+                    docs.push(doc! {field.as_str():doc! {"$elemMatch":doc! {"$nin":list}}});
+                }
+                condition::Expr::Compare {
+                    span: _,
+                    lhs,
+                    cmp,
+                    rhs,
+                } => {
+                    docs.push(Self::gen_arith_doc(lhs, cmp, rhs)?);
+                }
+                condition::Expr::Any {
+                    span: _,
+                    field,
+                    expr,
+                } => {
+                    let inner = Self::gen_elem_doc(self.eval(&[*expr.clone()])?)?;
+                    docs.push(doc! {field.as_str():doc! {"$elemMatch":inner}});
+                }
+                condition::Expr::All {
+                    span: _,
+                    field,
+                    expr,
+                } => {
+                    // 数组非空且不存在不满足子表达式的元素
+                    let inner = Self::gen_elem_doc(self.eval(&[*expr.clone()])?)?;
+                    docs.push(doc! {"$and": [
+                        {format!("{}.0", field):doc! {"$exists":true}},
+                        {field.as_str():doc! {"$not":doc! {"$elemMatch":doc! {"$nor":[inner]}}}},
+                    ]});
+                }
+                condition::Expr::Match {
+                    span: _,
+                    field,
+                    op,
+                    value,
+                    flags,
+                } => {
+                    let text = match value {
+                        condition::Value::Text(text) => text,
+                        _ => return Err(anyhow::anyhow!("match op just only support text")),
+                    };
+                    let mut ops = doc! {"$regex": op.pattern(text)};
+                    if !flags.is_empty() {
+                        ops.insert("$options", flags.as_str());
+                    }
+                    docs.push(doc! {field.as_str(): ops});
+                }
+                condition::Expr::NotMatch {
+                    span: _,
+                    field,
+                    value,
+                    flags,
+                } => {
+                    let text = match value {
+                        condition::Value::Text(text) => text,
+                        _ => return Err(anyhow::anyhow!("not like op just only support text")),
+                    };
+                    // 与 NotLike 一样要求字段是字符串，缺失或非字符串字段不满足
+                    let mut regex = doc! {"$regex": text.as_str()};
+                    if !flags.is_empty() {
+                        regex.insert("$options", flags.as_str());
+                    }
+                    docs.push(doc! {field.as_str(): doc! {"$type": "string", "$not": regex}});
+                }
+                condition::Expr::Between {
+                    span: _,
+                    field,
+                    lower,
+                    upper,
+                } => {
+                    // 两个端点合并到同一个操作符文档 {a: {$gte: 1, $lt: 10}}
+                    let (lower_op, upper_op) = match (lower.is_inclusive(), upper.is_inclusive()) {
+                        (true, true) => (MongoOp::Gte, MongoOp::Lte),
+                        (true, false) => (MongoOp::Gte, MongoOp::Lt),
+                        (false, true) => (MongoOp::Gt, MongoOp::Lte),
+                        (false, false) => (MongoOp::Gt, MongoOp::Lt),
+                    };
+                    let mut ops = doc! {};
+                    for (v, op) in [(lower.value(), lower_op), (upper.value(), upper_op)] {
+                        let doc = Self::gen_doc(&self.2, field.as_str(), v, op)?;
+                        ops.extend(doc.get_document(field.as_str())?.clone());
+                    }
+                    docs.push(doc! {field.as_str(): ops});
+                }
+                condition::Expr::Has {
+                    span: _,
+                    field,
+                    value,
+                } => {
+                    let list = Self::gen_list(&self.2, field.as_str(), value)?;
+                    docs.push(doc! {field.as_str():doc! {"$all":list}});
+                }
             }
         }
         Ok(docs)
@@ -285,16 +698,75 @@ impl Filter for MongoFilter {
         self
     }
 
-    fn parse<S: ToString + ?Sized>(&mut self, input: &S) -> anyhow::Result<Box<Self>> {
-        let expr = match parse(input) {
-            Ok(s) => s,
-            Err(e) => return Err(anyhow::anyhow!("{:?}", e)),
-        };
-        self.0 = self.eval(&[expr])?.into_iter().flatten().collect();
+    fn parse_with<S: ToString + ?Sized>(
+        &mut self,
+        input: &S,
+        params: &Params,
+    ) -> anyhow::Result<Box<Self>> {
+        // 保留 ParseError 类型，调用方可以通过 downcast 获取出错位置
+        let expr = parse_with(input, params)?;
+        self.parse_expr(expr)?;
         self.1 = input.to_string();
 
         Ok(Box::new(self.clone()))
     }
+
+    // 条件原文为表达式的输出
+    fn parse_expr(&mut self, expr: condition::Expr) -> anyhow::Result<Box<Self>> {
+        let expr = expr.normalize();
+        self.0 = self
+            .eval(std::slice::from_ref(&expr))?
+            .into_iter()
+            .flatten()
+            .collect();
+        self.1 = expr.to_string();
+        self.3 = Some(expr);
+
+        Ok(Box::new(self.clone()))
+    }
+
+    fn check(&mut self, schema: &crate::Schema, coerce: bool) -> anyhow::Result<()> {
+        let expr = match self.3.clone() {
+            Some(expr) if coerce => expr.coerce(schema)?,
+            Some(expr) => {
+                expr.check(schema)?;
+                expr
+            }
+            None => return Ok(()),
+        };
+        self.0 = self
+            .eval(std::slice::from_ref(&expr))?
+            .into_iter()
+            .flatten()
+            .collect();
+        self.3 = Some(expr);
+        Ok(())
+    }
+
+    fn check_limits(&self, limits: &crate::ParseLimits) -> anyhow::Result<()> {
+        if let Some(expr) = &self.3 {
+            expr.check_limits(limits)
+                .map_err(|e| e.with_input(&self.1))?;
+        }
+        Ok(())
+    }
+
+    // ! 内引用了不允许的字段或全部条件项都被去掉时返回 ForbiddenField，不会变成不过滤
+    fn check_fields(&mut self, policy: &crate::FieldPolicy, strip: bool) -> anyhow::Result<()> {
+        let expr = match self.3.clone() {
+            Some(expr) if strip => expr
+                .strip_fields(policy)
+                .map_err(|e| e.with_input(&self.1))?,
+            Some(expr) => {
+                expr.check_fields(policy)
+                    .map_err(|e| e.with_input(&self.1))?;
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        self.parse_expr(expr)?;
+        Ok(())
+    }
 }
 
 impl GetFilter for MongoFilter {
@@ -309,6 +781,10 @@ impl GetFilter for MongoFilter {
     fn get(&self) -> (Document, String) {
         (self.0.clone(), self.1.clone())
     }
+
+    fn get_expr(&self) -> Option<condition::Expr> {
+        self.3.clone()
+    }
 }
 
 #[cfg(test)]
@@ -317,7 +793,7 @@ mod test {
     #[test]
     fn test_parse_cond() {
         let sym = "a=1 && b=2 || c=1 && b=2";
-        let mut mf = MongoFilter(doc! {}, sym.into(), false);
+        let mut mf = MongoFilter(doc! {}, sym.into(), false, None);
         match mf.parse(sym) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -327,7 +803,7 @@ mod test {
     #[test]
     fn test_parse_cond2() {
         let sym = "a=1 && (b=2||c=1) && b=2";
-        let mut mf = MongoFilter(doc! {}, sym.to_string(), false);
+        let mut mf = MongoFilter(doc! {}, sym.to_string(), false, None);
         match mf.parse(sym) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -337,7 +813,7 @@ mod test {
     #[test]
     fn test_parse_cond3() {
         let sym = r#"a=1 && (b="2" || c=1 && b='2')"#;
-        let mut mf = MongoFilter(doc! {}, sym.to_string(), false);
+        let mut mf = MongoFilter(doc! {}, sym.to_string(), false, None);
         match mf.parse(sym) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -346,7 +822,7 @@ mod test {
 
     #[test]
     fn test_parse_in_notin() {
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse("a ~ (1,2,3,4)") {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -356,11 +832,35 @@ mod test {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
         }
+
+        // 绑定空列表时不匹配任何数据，而不是生成 {} 匹配全部
+        let params = Params::new().bind("ids", Vec::<i32>::new());
+        match mf.parse_with("a ~ :ids", &params) {
+            Ok(c) => assert_eq!(c.0, doc! {"a": {"$in": []}}),
+            Err(e) => panic!("{}", e),
+        }
+        match mf.parse_with("a ~~ :ids", &params) {
+            Ok(c) => assert_eq!(c.0, doc! {"a": {"$nin": []}}),
+            Err(e) => panic!("{}", e),
+        }
+        let expr = condition::yacc_parse_with("a ~ :ids", &params).unwrap();
+        assert!(!crate::utils::matchs::Predicate::new(expr)
+            .unwrap()
+            .matches(&crate::utils::from_str(r#"{"a":1}"#).unwrap()));
+
+        // bool 和 null 可以作为列表元素，null 可以与其它类型一起出现
+        match mf.parse("a ~ (true, null) && b ~~ (1, null)") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [{"a": {"$in": [true, Bson::Null]}}, {"b": {"$nin": [1_i64, Bson::Null]}}]}
+            ),
+            Err(e) => panic!("{}", e),
+        }
     }
 
     #[test]
     fn test_parse_like() {
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse("a ! '^1.2'") {
             // prefix 1.2
             Ok(c) => println!("{:?}", c),
@@ -370,7 +870,7 @@ mod test {
 
     #[test]
     fn test_parse_f64() {
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse("a = 1.2") {
             // prefix 1.2
             Ok(c) => println!("{:?}", c),
@@ -380,7 +880,7 @@ mod test {
 
     #[test]
     fn test_parse_strings() {
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse(r#"a = 1.2 || b = 'abc' ||c="cde""#) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -389,13 +889,13 @@ mod test {
 
     #[test]
     fn test_parse_in_strings() {
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse(r#"a ~ ("1","2")"#) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
         };
 
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse(r#"a ~ ('1','2')"#) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -404,7 +904,7 @@ mod test {
 
     #[test]
     fn test_parse_isodate() {
-        let mut mf = MongoFilter(doc! {}, "".to_string(), false);
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
         match mf.parse(r#"a >= 'ISODate(2021-01-01 00:00:00)'"#) {
             Ok(c) => println!("{:?}", c),
             Err(e) => panic!("{}", e),
@@ -416,4 +916,442 @@ mod test {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[test]
+    fn test_parse_not_like() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("a !! '^1.2'") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"a": {"$type": "string", "$not": {"$regex": "^1.2"}}}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        // 字段缺失时不匹配，与 matchs 一致
+        let predicate = crate::utils::matchs::Predicate::parse("a !! '^1.2'").unwrap();
+        assert!(!predicate.matches(&crate::utils::from_str(r#"{"b":"x"}"#).unwrap()));
+        assert!(predicate.matches(&crate::utils::from_str(r#"{"a":"x"}"#).unwrap()));
+
+        // 带选项时与不带选项一致，缺失或非字符串字段都不匹配
+        let predicate = crate::utils::matchs::Predicate::parse("a !! '^1.2' i").unwrap();
+        for (json, ok) in [
+            (r#"{"b":"x"}"#, false),
+            (r#"{"a":1}"#, false),
+            (r#"{"a":"x"}"#, true),
+        ] {
+            assert_eq!(
+                predicate.matches(&crate::utils::from_str(json).unwrap()),
+                ok,
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_null() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("a ^ null && b ^^ null") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [{"a": Bson::Null}, {"b": {"$ne": Bson::Null}}]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_len() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("len(name) > 5 || len(ids) != 0") {
+            Ok(c) => println!("{:?}", c),
+            Err(e) => panic!("{}", e),
+        };
+
+        // 多字节字符串按字符计数，与 matchs 的结果一致
+        let data = crate::utils::from_str(r#"{"name":"张三丰"}"#).unwrap();
+        let predicate = crate::utils::matchs::Predicate::parse("len(name) = 3").unwrap();
+        assert!(predicate.matches(&data));
+        let c = mf.parse("len(name) = 3").unwrap();
+        let size =
+            c.0.get_document("$expr")
+                .unwrap()
+                .get_document("$let")
+                .unwrap();
+        let branches = size
+            .get_document("vars")
+            .unwrap()
+            .get_document("len")
+            .unwrap()
+            .get_document("$switch")
+            .unwrap()
+            .get_array("branches")
+            .unwrap();
+        assert_eq!(
+            branches[1]
+                .as_document()
+                .unwrap()
+                .get_document("then")
+                .unwrap(),
+            &doc! {"$strLenCP": "$name"}
+        );
+    }
+
+    #[test]
+    fn test_parse_belong() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("a << (1,2,3)") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [
+                    {"a.0": {"$exists": true}},
+                    {"a": {"$not": {"$elemMatch": {"$nin": [1_i64, 2_i64, 3_i64]}}}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        match mf.parse("a >> ('x','y')") {
+            Ok(c) => assert_eq!(c.0, doc! {"a": {"$elemMatch": {"$nin": ["x", "y"]}}}),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_not() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("!(a > 1)") {
+            Ok(c) => assert_eq!(c.0, doc! {"a": {"$not": {"$gt": 1_i64}}}),
+            Err(e) => panic!("{}", e),
+        };
+
+        match mf.parse("NOT a = 1") {
+            Ok(c) => assert_eq!(c.0, doc! {"a": {"$not": {"$eq": 1_i64}}}),
+            Err(e) => panic!("{}", e),
+        };
+
+        match mf.parse("!(a = 1 || b = 2) && c = 3") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [
+                    {"$nor": [{"$or": [{"a": {"$eq": 1_i64}}, {"b": {"$eq": 2_i64}}]}]},
+                    {"c": {"$eq": 3_i64}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_normalize() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("(a = 1 && b = 2) && (c = 3 && a = 1)") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [
+                    {"a": {"$eq": 1_i64}},
+                    {"b": {"$eq": 2_i64}},
+                    {"c": {"$eq": 3_i64}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        match mf.parse("a = 1 || a = 2 || b = 'x'") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$or": [
+                    {"a": {"$in": [1_i64, 2_i64]}},
+                    {"b": {"$eq": "x"}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        let dt = bson::DateTime::from_millis(1655107200000);
+        match mf.parse("t >= 2022-06-13T16:00:00+08:00 && t < @1655110800") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [
+                    {"t": {"$gte": dt}},
+                    {"t": {"$lt": bson::DateTime::from_millis(1655110800000)}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        match mf.parse("t ~ (2022-06-13T08:00:00Z,@0)") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"t": {"$in": [dt, bson::DateTime::from_millis(0)]}}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_field_compare() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("updated_at > created_at") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$expr": {"$and": [
+                    {"$gt": ["$updated_at", "$created_at"]},
+                    {"$gt": ["$updated_at", Bson::Null]},
+                    {"$gt": ["$created_at", Bson::Null]},
+                ]}}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_arith() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        let price = doc! {"$cond": [{"$isNumber": "$price"}, "$price", Bson::Null]};
+        let qty = doc! {"$cond": [{"$isNumber": "$qty"}, "$qty", Bson::Null]};
+        match mf.parse("price * qty >= 100") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$expr": {"$let": {
+                    "vars": {"lhs": {"$multiply": [&price, &qty]}, "rhs": 100_i64},
+                    "in": {"$and": [
+                        {"$ne": ["$$lhs", Bson::Null]},
+                        {"$ne": ["$$rhs", Bson::Null]},
+                        {"$or": [
+                            {"$and": [{"$isNumber": "$$lhs"}, {"$isNumber": "$$rhs"}]},
+                            {"$eq": [{"$type": "$$lhs"}, {"$type": "$$rhs"}]},
+                        ]},
+                        {"$gte": ["$$lhs", "$$rhs"]},
+                    ]},
+                }}}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("price / qty > 2") {
+            Ok(c) => assert_eq!(
+                c.0.get_document("$expr")
+                    .and_then(|d| d.get_document("$let"))
+                    .and_then(|d| d.get_document("vars"))
+                    .and_then(|d| d.get_document("lhs"))
+                    .unwrap(),
+                &doc! {"$cond": [{"$eq": [&qty, 0]}, Bson::Null, {"$divide": [&price, &qty]}]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        // 占位符绑定后参与运算
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        let params = Params::new().bind("n", 1);
+        match mf.parse_with("len(tags) + :n > limit", &params) {
+            Ok(c) => assert!(c.0.contains_key("$expr")),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_function() {
+        let lhs = |c: &MongoFilter| {
+            c.0.get_document("$expr")
+                .and_then(|d| d.get_document("$let"))
+                .and_then(|d| d.get_document("vars"))
+                .ok()
+                .and_then(|d| d.get("lhs"))
+                .cloned()
+                .unwrap()
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("lower(name) = 'bill'") {
+            Ok(c) => assert_eq!(
+                lhs(&c),
+                Bson::Document(doc! {"$cond": [
+                    {"$eq": [{"$type": "$name"}, "string"]},
+                    {"$toLower": "$name"},
+                    Bson::Null,
+                ]})
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("date_trunc('day', t) < now()") {
+            Ok(c) => assert_eq!(
+                lhs(&c),
+                Bson::Document(doc! {"$cond": [
+                    {"$eq": [{"$type": "$t"}, "date"]},
+                    {"$dateTrunc": {"date": "$t", "unit": {"$literal": "day"}}},
+                    Bson::Null,
+                ]})
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("exists(a) = true") {
+            Ok(c) => assert_eq!(
+                lhs(&c),
+                Bson::Document(doc! {"$ne": [{"$type": "$a"}, "missing"]})
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        crate::utils::Function::new("half", 1, 1)
+            .mongo(|args| Ok(Bson::Document(doc! {"$divide": [args[0].clone(), 2]})))
+            .register()
+            .unwrap();
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("half(a) > 1") {
+            Ok(c) => assert_eq!(lhs(&c), Bson::Document(doc! {"$divide": ["$a", 2]})),
+            Err(e) => panic!("{}", e),
+        };
+
+        // 没有 mongodb 实现的函数返回错误
+        crate::utils::Function::new("score_only_in_memory", 1, 1)
+            .eval(|args| args[0].as_i64().map(Into::into))
+            .register()
+            .unwrap();
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("score_only_in_memory(a) > 1") {
+            Ok(c) => panic!("unexpected result {:?}", c.0),
+            Err(e) => assert!(e.to_string().contains("not supported by mongodb"), "{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_elem() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("any(lines, price > 10 && qty > 1)") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"lines": {"$elemMatch": {"$and": [
+                    {"price": {"$gt": 10_i64}},
+                    {"qty": {"$gt": 1_i64}},
+                ]}}}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("all(lines, qty > 0)") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [
+                    {"lines.0": {"$exists": true}},
+                    {"lines": {"$not": {"$elemMatch": {"$nor": [{"qty": {"$gt": 0_i64}}]}}}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("tags has ('x','y')") {
+            Ok(c) => assert_eq!(c.0, doc! {"tags": {"$all": ["x", "y"]}}),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        if let Ok(c) = mf.parse("any(lines, price * qty > 10)") {
+            panic!("unexpected result {:?}", c.0);
+        }
+    }
+
+    #[test]
+    fn test_parse_between() {
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("age between [18, 60)") {
+            Ok(c) => assert_eq!(c.0, doc! {"age": {"$gte": 18_i64, "$lt": 60_i64}}),
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse("t between (@0, @60] && name = 'a'") {
+            Ok(c) => assert_eq!(
+                c.0,
+                doc! {"$and": [
+                    {"t": {
+                        "$gt": Bson::DateTime(bson::DateTime::from_millis(0)),
+                        "$lte": Bson::DateTime(bson::DateTime::from_millis(60_000)),
+                    }},
+                    {"name": {"$eq": "a"}},
+                ]}
+            ),
+            Err(e) => panic!("{}", e),
+        };
+    }
+
+    #[test]
+    fn test_parse_match() {
+        let cases = [
+            ("name contains 'a.(b'", doc! {"name": {"$regex": r"a\.\(b"}}),
+            (
+                "name startswith 'bill' i",
+                doc! {"name": {"$regex": r"\Abill", "$options": "i"}},
+            ),
+            (
+                "mail endswith '.com'",
+                doc! {"mail": {"$regex": r"\.com\z"}},
+            ),
+            (
+                "name ieq 'Bill'",
+                doc! {"name": {"$regex": r"\ABill\z", "$options": "i"}},
+            ),
+            (
+                "name ! '^b.l' mi",
+                doc! {"name": {"$regex": "^b.l", "$options": "im"}},
+            ),
+            (
+                "name !! '^b' i",
+                doc! {"name": {"$type": "string", "$not": {"$regex": "^b", "$options": "i"}}},
+            ),
+        ];
+        for (sym, expected) in cases {
+            let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+            match mf.parse(sym) {
+                Ok(c) => assert_eq!(c.0, expected, "{}", sym),
+                Err(e) => panic!("{}", e),
+            };
+        }
+    }
+
+    #[test]
+    fn test_parse_expr() {
+        use crate::field;
+
+        let sym = "age > 3 && name ~ ('a','b') && tags has 'x'";
+        let expr = field("age")
+            .gt(3)
+            .and(field("name").is_in(["a", "b"]))
+            .and(field("tags").has(["x"]));
+
+        let mut expected = MongoFilter(doc! {}, "".to_string(), false, None);
+        expected.parse(sym).unwrap();
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse_expr(expr) {
+            Ok(c) => {
+                assert_eq!(c.0, expected.0);
+                assert_eq!(c.1, sym);
+            }
+            Err(e) => panic!("{}", e),
+        };
+
+        // 前端提交的 JSON 条件
+        let json = r#"{"version": 1, "expr": {"op": "and",
+            "lhs": {"op": "gt", "field": "age", "value": {"number": 3}},
+            "rhs": {"op": "and",
+                "lhs": {"op": "in", "field": "name", "value": {"list": [{"text": "a"}, {"text": "b"}]}},
+                "rhs": {"op": "has", "field": "tags", "value": {"list": [{"text": "x"}]}}}}}"#;
+        let mut mf = MongoFilter(doc! {}, "".to_string(), false, None);
+        match mf.parse_expr(crate::Expr::from_json_str(json).unwrap()) {
+            Ok(c) => assert_eq!(c.0, expected.0),
+            Err(e) => panic!("{}", e),
+        };
+    }
 }
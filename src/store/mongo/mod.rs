@@ -1,6 +1,5 @@
 mod filter;
 use chrono::NaiveDateTime;
-use condition::yacc_parse as parse;
 pub use filter::MongoFilter;
 use mongodb::change_stream::event::ChangeStreamEvent;
 mod extends;
@@ -10,17 +9,18 @@ use super::{Event, Filter};
 use super::{Storage, StoreError};
 use crate::object::Object;
 
-use crate::store::mongo::matchs::matchs;
 use crate::store::Result;
 use crate::utils::dict::{compare_and_merge, from_value_to_unstructed, get, value_to_map};
-use crate::utils::{matchs, Unstructed};
+use crate::utils::matchs::Predicate;
+use crate::utils::Unstructed;
 
 use bson::oid::ObjectId;
 use bson::{doc, Bson, Document};
 
 use futures::{Future, TryStreamExt};
 use mongodb::options::{
-    ChangeStreamOptions, FindOptions, FullDocumentType, TransactionOptions, UpdateOptions,
+    ChangeStreamOptions, FindOneOptions, FindOptions, FullDocumentType, TransactionOptions,
+    UpdateOptions,
 };
 use mongodb::{change_stream, Client};
 use std::env;
@@ -45,17 +45,45 @@ pub fn convert_to_mongodb_time(datetime_str: &str) -> anyhow::Result<bson::DateT
 }
 
 pub fn new_mongo_condition() -> Condition<MongoFilter> {
-    Condition::new(MongoFilter(doc! {}, "".to_string(), false))
+    Condition::new(MongoFilter(doc! {}, "".to_string(), false, None))
 }
 
 fn uuid() -> String {
     ObjectId::new().to_string()
 }
 
+// list、get 返回的字段，为空时返回全部字段
+fn projection_doc(fields: &[String]) -> Option<Document> {
+    if fields.is_empty() {
+        return None;
+    }
+    Some(
+        fields
+            .iter()
+            .map(|field| (field.clone(), Bson::from(1)))
+            .collect(),
+    )
+}
+
+// 带 projection 时按 Document 读取再解码，缺少类型必需的字段时返回 ProjectionError
+fn decode<T: DeserializeOwned>(doc: Document, projection: &[String]) -> Result<T> {
+    bson::from_document(doc).map_err(|e| match projection.is_empty() {
+        true => StoreError::OtherError(e.to_string()),
+        false => StoreError::projection(projection, e),
+    })
+}
+
 pub trait GetFilter {
     fn get_doc(self) -> Document;
     fn get_src(self) -> String;
     fn get(&self) -> (Document, String);
+    // 未设置条件时返回 None
+    fn get_expr(&self) -> Option<condition::Expr>;
+
+    // 编译后的条件，watch 等需要多次匹配时只编译一次
+    fn get_predicate(&self) -> anyhow::Result<Option<Predicate>> {
+        self.get_expr().map(Predicate::new).transpose()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -102,23 +130,24 @@ where
         Self: 'a;
     fn list<'r>(self, q: Condition<F>) -> Self::ListFuture<'r> {
         let block = async move {
+            let skip = q.skip();
             let Condition {
                 db,
                 table,
                 filter,
-                page,
                 size: page_size,
                 sorts,
                 pageable,
+                projection,
                 ..
             } = q;
 
-            let c = self.collection::<T>(&db, &table);
+            let c = self.collection::<Document>(&db, &table);
 
             let mut opt = FindOptions::builder().build();
 
             if pageable {
-                opt.skip = Some((page * page_size) as u64);
+                opt.skip = Some(skip as u64);
                 opt.limit = Some(page_size as i64);
             }
 
@@ -133,6 +162,8 @@ where
                 opt.sort = Some(doc);
             }
 
+            opt.projection = projection_doc(&projection);
+
             let mut cursor = c
                 .find(filter.get_doc())
                 .with_options(opt)
@@ -146,7 +177,7 @@ where
                 .await
                 .map_err(|e| StoreError::ConnectionError(e.to_string()))?
             {
-                items.push(item);
+                items.push(decode(item, &projection)?);
             }
 
             Ok(items)
@@ -162,16 +193,24 @@ where
     fn get<'r>(self, q: Condition<F>) -> Self::GetFuture<'r> {
         let block = async move {
             let Condition {
-                db, table, filter, ..
+                db,
+                table,
+                filter,
+                projection,
+                ..
             } = q;
-            let c = self.collection::<T>(&db, &table);
+            let c = self.collection::<Document>(&db, &table);
+
+            let mut opt = FindOneOptions::builder().build();
+            opt.projection = projection_doc(&projection);
 
             if let Some(value) = c
                 .find_one(filter.get_doc())
+                .with_options(opt)
                 .await
                 .map_err(|e| StoreError::ConnectionError(e.to_string()))?
             {
-                return Ok(value);
+                return decode(value, &projection);
             }
 
             Err(StoreError::DataNotFound.into())
@@ -193,7 +232,11 @@ where
         async move {
             let (tx, rx) = tokio::sync::mpsc::channel(1);
 
-            let (filter_doc, filter_src) = filter.get();
+            // 使用已绑定参数的表达式匹配变更，条件只编译一次
+            let (filter_doc, _) = filter.get();
+            let predicate = filter
+                .get_predicate()
+                .map_err(|e| StoreError::OtherError(e.to_string()))?;
             let collection = client.database(&db).collection::<T>(&table);
             let mut cursor = collection
                 .find(filter_doc.clone())
@@ -211,15 +254,14 @@ where
                 .map_err(|e| StoreError::ConnectionError(e.to_string()))?;
 
             let _matchs = move |item: &T| -> bool {
-                if filter_src.eq("") {
-                    return true;
-                }
-                if let Ok(v) = from_value_to_unstructed(item) {
-                    if let Ok(r) = matchs(&mut vec![v], parse(&filter_src).unwrap()) {
-                        return r.len() == 1;
-                    }
+                let predicate = match &predicate {
+                    Some(predicate) => predicate,
+                    None => return true,
+                };
+                match from_value_to_unstructed(item) {
+                    Ok(v) => predicate.matches(&v),
+                    Err(_) => false,
                 }
-                return false;
             };
 
             tokio::spawn(async move {
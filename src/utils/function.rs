@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use bson::Bson;
+use serde_json::Value;
+
+// matchs 中的求值，参数为求值后的值，字段缺失时为 null，返回 None 表示比较不成立
+pub type EvalFn = Arc<dyn Fn(&[Value]) -> Option<Value> + Send + Sync>;
+// mongodb 中的转换，参数为转换后的聚合表达式
+pub type MongoFn = Arc<dyn Fn(Vec<Bson>) -> anyhow::Result<Bson> + Send + Sync>;
+
+// 用户注册的条件函数，未提供某个后端的实现时，在该后端使用会返回错误
+// Function::new("double", 1, 1)
+//     .eval(|args| Some(json!(args[0].as_i64()? * 2)))
+//     .mongo(|args| Ok(bson!({"$multiply": [args[0].clone(), 2]})))
+//     .register()?;
+#[derive(Clone)]
+pub struct Function {
+    pub name: String,
+    pub min_args: usize,
+    pub max_args: usize,
+    eval: Option<EvalFn>,
+    mongo: Option<MongoFn>,
+}
+
+static FUNCTIONS: LazyLock<RwLock<HashMap<String, Function>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+impl Function {
+    pub fn new(name: &str, min_args: usize, max_args: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            min_args,
+            max_args,
+            eval: None,
+            mongo: None,
+        }
+    }
+
+    pub fn eval<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[Value]) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.eval = Some(Arc::new(f));
+        self
+    }
+
+    pub fn mongo<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Vec<Bson>) -> anyhow::Result<Bson> + Send + Sync + 'static,
+    {
+        self.mongo = Some(Arc::new(f));
+        self
+    }
+
+    // 注册后条件中才能解析该函数，不能覆盖内置函数
+    pub fn register(self) -> anyhow::Result<()> {
+        if !condition::register_function(&self.name, self.min_args, self.max_args) {
+            return Err(anyhow::anyhow!(
+                "function `{}` is builtin and can not be registered",
+                self.name
+            ));
+        }
+        FUNCTIONS.write().unwrap().insert(self.name.clone(), self);
+        Ok(())
+    }
+
+    pub fn get_eval(name: &str) -> anyhow::Result<EvalFn> {
+        Self::lookup(name)?
+            .eval
+            .ok_or_else(|| anyhow::anyhow!("function `{}` is not supported by matchs", name))
+    }
+
+    pub fn get_mongo(name: &str) -> anyhow::Result<MongoFn> {
+        Self::lookup(name)?
+            .mongo
+            .ok_or_else(|| anyhow::anyhow!("function `{}` is not supported by mongodb", name))
+    }
+
+    fn lookup(name: &str) -> anyhow::Result<Function> {
+        FUNCTIONS
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("unknown function `{}`", name))
+    }
+}
@@ -1,39 +1,488 @@
-use super::Unstructed;
-use condition::Expr;
+use super::{Function, Unstructed};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+use condition::visit::{walk_expr, Visitor};
+use condition::{ArithOp, Expr, MatchOp, Operand};
+use regex::{Regex, RegexBuilder};
 use serde_json::Value;
 
+// 按条件原文缓存的已编译条件数量，超出后淘汰最久未使用的条件
+pub const PREDICATE_CACHE_CAPACITY: usize = 256;
+
 pub fn match_by_predicate<'a>(
     unstructeds: &'a mut Vec<Unstructed>,
     predicate: &str,
 ) -> anyhow::Result<&'a mut Vec<Unstructed>> {
-    matchs(unstructeds, condition::yacc_parse(predicate)?)
+    let predicate = compile(predicate)?;
+    unstructeds.retain(|unstructed| predicate.matches(unstructed));
+    Ok(unstructeds)
 }
 
 pub fn matchs<'a>(
     unstructeds: &'a mut Vec<Unstructed>,
     expr: Expr,
 ) -> anyhow::Result<&'a mut Vec<Unstructed>> {
-    let mut remove_indexs = vec![];
-    for (index, unstructed) in unstructeds.into_iter().enumerate() {
-        if !filter(unstructed, &expr) {
-            remove_indexs.push(index);
+    let predicate = Predicate::new(expr)?;
+    unstructeds.retain(|unstructed| predicate.matches(unstructed));
+    Ok(unstructeds)
+}
+
+// 条件中的正则，按 (操作符, 原文, 选项) 查找，编译失败的正则不匹配任何值
+#[derive(Default)]
+struct Regexes(Vec<(MatchOp, String, String, Option<Regex>)>);
+
+impl Regexes {
+    fn build(op: &MatchOp, text: &str, flags: &str) -> Option<Regex> {
+        RegexBuilder::new(&op.pattern(text))
+            .case_insensitive(flags.contains('i'))
+            .multi_line(flags.contains('m'))
+            .build()
+            .ok()
+    }
+
+    fn add(&mut self, op: &MatchOp, text: &str, flags: &str) {
+        if !self
+            .0
+            .iter()
+            .any(|(o, t, f, _)| o == op && t == text && f == flags)
+        {
+            let regex = Self::build(op, text, flags);
+            self.0
+                .push((op.clone(), text.to_string(), flags.to_string(), regex));
         }
     }
-    remove_indexs.sort_by_key(|n| std::usize::MAX - n);
 
-    for index in remove_indexs {
-        unstructeds.remove(index);
+    fn compile(&mut self, expr: &Expr) {
+        self.visit_expr(expr)
     }
 
-    Ok(unstructeds)
+    // 正则编译失败时返回 None
+    fn is_match(&self, op: &MatchOp, text: &str, flags: &str, s: &str) -> Option<bool> {
+        match self
+            .0
+            .iter()
+            .find(|(o, t, f, _)| o == op && t == text && f == flags)
+        {
+            Some((.., regex)) => regex.as_ref().map(|r| r.is_match(s)),
+            None => Self::build(op, text, flags).map(|r| r.is_match(s)),
+        }
+    }
+}
+
+// 只关心正则匹配，其余节点按默认方式遍历
+impl Visitor for Regexes {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Like {
+                value: condition::Value::Text(t),
+                ..
+            }
+            | Expr::NotLike {
+                value: condition::Value::Text(t),
+                ..
+            } => self.add(&MatchOp::Regex, t, ""),
+            Expr::Match {
+                op,
+                value: condition::Value::Text(t),
+                flags,
+                ..
+            } => self.add(op, t, flags),
+            Expr::NotMatch {
+                value: condition::Value::Text(t),
+                flags,
+                ..
+            } => self.add(&MatchOp::Regex, t, flags),
+            expr => walk_expr(self, expr),
+        }
+    }
+}
+
+// 编译后的条件: 创建时检查函数并预编译正则，可在多次匹配和多个线程间共享
+// let predicate = Predicate::new(expr)?;
+// items.retain(|item| predicate.matches(item));
+#[derive(Clone)]
+pub struct Predicate(Arc<(Expr, Regexes)>);
+
+impl Predicate {
+    pub fn new(expr: Expr) -> anyhow::Result<Self> {
+        check(&expr)?;
+        let mut regexes = Regexes::default();
+        regexes.compile(&expr);
+        Ok(Self(Arc::new((expr, regexes))))
+    }
+
+    // 不经过缓存，直接解析条件原文
+    pub fn parse(src: &str) -> anyhow::Result<Self> {
+        Self::new(condition::yacc_parse(src)?.normalize())
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.0 .0
+    }
+
+    pub fn matches(&self, unstructed: &Unstructed) -> bool {
+        filter(unstructed, &self.0 .0, &self.0 .1)
+    }
+}
+
+impl std::fmt::Debug for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Predicate({})", self.expr())
+    }
+}
+
+// 进程内按条件原文缓存的已编译条件，tick 越小越久未使用
+#[derive(Default)]
+struct PredicateCache {
+    tick: u64,
+    items: HashMap<String, (u64, Predicate)>,
+}
+
+static PREDICATES: LazyLock<Mutex<PredicateCache>> =
+    LazyLock::new(|| Mutex::new(PredicateCache::default()));
+
+// 解析并编译条件原文，相同的原文复用缓存，解析失败的条件不缓存
+// 绑定参数的条件原文相同但值不同，应使用 Predicate::new
+pub fn compile(src: &str) -> anyhow::Result<Predicate> {
+    {
+        let mut cache = PREDICATES.lock().unwrap();
+        cache.tick += 1;
+        let tick = cache.tick;
+        if let Some((used, predicate)) = cache.items.get_mut(src) {
+            *used = tick;
+            return Ok(predicate.clone());
+        }
+    }
+
+    let predicate = Predicate::parse(src)?;
+    let mut cache = PREDICATES.lock().unwrap();
+    let tick = cache.tick;
+    cache
+        .items
+        .insert(src.to_string(), (tick, predicate.clone()));
+    if cache.items.len() > PREDICATE_CACHE_CAPACITY {
+        let oldest = cache
+            .items
+            .iter()
+            .min_by_key(|(_, (used, _))| *used)
+            .map(|(src, _)| src.clone());
+        if let Some(oldest) = oldest {
+            cache.items.remove(&oldest);
+        }
+    }
+    Ok(predicate)
+}
+
+// 字段中的时间: RFC 3339 或 "YYYY-mm-dd HH:MM:SS"(UTC) 字符串，
+// 以及 mongodb 扩展 JSON {"$date": ...}
+fn datetime_of(value: &Value) -> Option<DateTime<FixedOffset>> {
+    match value {
+        Value::String(s) => DateTime::parse_from_rfc3339(s).ok().or_else(|| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|dt| Utc.from_utc_datetime(&dt).into())
+        }),
+        Value::Object(map) => match map.get("$date")? {
+            Value::Number(ms) => Utc
+                .timestamp_millis_opt(ms.as_i64()?)
+                .single()
+                .map(Into::into),
+            Value::Object(long) => {
+                let ms = long.get("$numberLong")?.as_str()?.parse::<i64>().ok()?;
+                Utc.timestamp_millis_opt(ms).single().map(Into::into)
+            }
+            date => datetime_of(date),
+        },
+        _ => None,
+    }
+}
+
+// 字段间比较，两侧同为数字、字符串、布尔或时间时才可比较，字段缺失时不可比较
+fn compare_field(unstructed: &Unstructed, lhs: &Value, field: &str) -> Option<Ordering> {
+    compare_values(lhs, lookup(unstructed, field)?)
+}
+
+fn compare_values(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    if let (Some(l), Some(r)) = (datetime_of(lhs), datetime_of(rhs)) {
+        return Some(l.cmp(&r));
+    }
+    match (lhs, rhs) {
+        (Value::Number(l), Value::Number(r)) => Num::of(l)?.compare(Num::of(r)?),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::Bool(l), Value::Bool(r)) => Some(l.cmp(r)),
+        _ => None,
+    }
+}
+
+// 字符串、数组或对象的长度，字符串按字符数计算，与 mongodb 的 $strLenCP 一致
+fn len_of(unstructed: &Unstructed, field: &str) -> Option<i64> {
+    match lookup(unstructed, field)? {
+        Value::String(v) => Some(v.chars().count() as i64),
+        Value::Array(v) => Some(v.len() as i64),
+        Value::Object(v) => Some(v.len() as i64),
+        _ => None,
+    }
+}
+
+// 按 a.b.c 查找字段，字段缺失时为 None，值为 null 时为 Some(Null)
+fn lookup<'a>(unstructed: &'a Unstructed, path: &str) -> Option<&'a Value> {
+    let mut keys = path.split('.');
+    let mut value = unstructed.0.get(keys.next()?)?;
+    for key in keys {
+        value = value.as_object()?.get(key)?;
+    }
+    Some(value)
+}
+
+// 算术运算的值，整数之间的运算保持精确，溢出或不能整除时使用浮点数
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Num {
+    Int(i64),
+    Float(f64),
+}
+
+impl Num {
+    fn of(n: &serde_json::Number) -> Option<Num> {
+        match n.as_i64() {
+            Some(v) => Some(Num::Int(v)),
+            None => n.as_f64().map(Num::Float),
+        }
+    }
+
+    fn value(self) -> Option<Value> {
+        match self {
+            Num::Int(v) => Some(Value::from(v)),
+            Num::Float(v) => serde_json::Number::from_f64(v).map(Value::Number),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Num::Int(v) => v as f64,
+            Num::Float(v) => v,
+        }
+    }
+
+    fn compare(self, other: Num) -> Option<Ordering> {
+        match (self, other) {
+            (Num::Int(l), Num::Int(r)) => Some(l.cmp(&r)),
+            (l, r) => l.as_f64().partial_cmp(&r.as_f64()),
+        }
+    }
+}
+
+// 除数为 0 时没有结果，比较不成立
+fn arith(op: &ArithOp, lhs: Num, rhs: Num) -> Option<Num> {
+    if let (Num::Int(l), Num::Int(r)) = (lhs, rhs) {
+        let exact = match op {
+            ArithOp::Add => l.checked_add(r),
+            ArithOp::Sub => l.checked_sub(r),
+            ArithOp::Mul => l.checked_mul(r),
+            ArithOp::Div => l
+                .checked_rem(r)
+                .filter(|m| *m == 0)
+                .and_then(|_| l.checked_div(r)),
+            ArithOp::Rem => l.checked_rem(r),
+        };
+        if let Some(v) = exact {
+            return Some(Num::Int(v));
+        }
+    }
+    let (l, r) = (lhs.as_f64(), rhs.as_f64());
+    let v = match op {
+        ArithOp::Add => l + r,
+        ArithOp::Sub => l - r,
+        ArithOp::Mul => l * r,
+        ArithOp::Div | ArithOp::Rem if r == 0.0 => return None,
+        ArithOp::Div => l / r,
+        ArithOp::Rem => l % r,
+    };
+    v.is_finite().then_some(Num::Float(v))
+}
+
+fn number(value: &Value) -> Option<Num> {
+    match value {
+        Value::Number(n) => Num::of(n),
+        _ => None,
+    }
+}
+
+// 操作数的值，字段缺失、不是数字的算术运算、除数为 0 时没有结果
+// 时间以 RFC 3339 字符串表示，比较时按时间比较
+fn eval(unstructed: &Unstructed, operand: &Operand) -> Option<Value> {
+    match operand {
+        Operand::Field(field) => lookup(unstructed, field).cloned(),
+        Operand::Value(value) => json_of(value),
+        Operand::Len(field) => len_of(unstructed, field).map(Value::from),
+        Operand::Arith { op, lhs, rhs } => {
+            let lhs = number(&eval(unstructed, lhs)?)?;
+            let rhs = number(&eval(unstructed, rhs)?)?;
+            arith(op, lhs, rhs)?.value()
+        }
+        Operand::Call { name, args } => call(unstructed, name, args),
+    }
 }
 
-fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
+fn json_of(value: &condition::Value) -> Option<Value> {
+    match value {
+        condition::Value::Text(v) => Some(Value::String(v.clone())),
+        condition::Value::Number(v) => Some(Value::Number(v.clone())),
+        condition::Value::Bool(v) => Some(Value::Bool(*v)),
+        condition::Value::DateTime(v) => Some(Value::String(v.to_rfc3339())),
+        _ => None,
+    }
+}
+
+// 列表中是否有与字段值相等的元素，null 与缺失的字段或 null 相等
+fn list_contains(list: &[condition::Value], value: Option<&Value>) -> bool {
+    list.iter().any(|item| match (item, value) {
+        (condition::Value::Null, None | Some(Value::Null)) => true,
+        (condition::Value::DateTime(t), Some(s)) => datetime_of(s).is_some_and(|dt| t.eq(&dt)),
+        (condition::Value::Number(t), Some(Value::Number(s))) => t.eq(s),
+        (condition::Value::Text(t), Some(Value::String(s))) => t.eq(s),
+        (condition::Value::Bool(t), Some(Value::Bool(s))) => t == s,
+        _ => false,
+    })
+}
+
+// 数组中的对象元素，其余元素不满足任何子表达式
+fn elements<'a>(unstructed: &'a Unstructed, field: &str) -> Option<&'a Vec<Value>> {
+    lookup(unstructed, field)?.as_array()
+}
+
+fn element_matches(item: &Value, expr: &Expr, regexes: &Regexes) -> bool {
+    match item {
+        Value::Object(map) => filter(&Unstructed(map.clone()), expr, regexes),
+        _ => false,
+    }
+}
+
+// 与 mongodb 的 $type 一致，数字统一为 number
+fn type_of(value: Option<&Value>) -> &'static str {
+    match value {
+        None => "missing",
+        Some(Value::Null) => "null",
+        Some(Value::Bool(_)) => "bool",
+        Some(Value::Number(_)) => "number",
+        Some(Value::String(_)) => "string",
+        Some(Value::Array(_)) => "array",
+        Some(Value::Object(map)) if map.contains_key("$date") => "date",
+        Some(Value::Object(_)) => "object",
+    }
+}
+
+// 按 UTC 截断时间，week 从周日开始
+fn date_trunc(unit: &str, dt: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    let dt = dt.naive_utc();
+    let date = dt.date();
+    let (h, m, s) = (dt.hour(), dt.minute(), dt.second());
+    let start = match unit {
+        "year" => NaiveDate::from_ymd_opt(date.year(), 1, 1)?.and_hms_opt(0, 0, 0)?,
+        "quarter" => {
+            let month = date.month0() / 3 * 3 + 1;
+            NaiveDate::from_ymd_opt(date.year(), month, 1)?.and_hms_opt(0, 0, 0)?
+        }
+        "month" => NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?.and_hms_opt(0, 0, 0)?,
+        "week" => {
+            let days = date.weekday().num_days_from_sunday() as i64;
+            (date - Duration::days(days)).and_hms_opt(0, 0, 0)?
+        }
+        "day" => date.and_hms_opt(0, 0, 0)?,
+        "hour" => date.and_hms_opt(h, 0, 0)?,
+        "minute" => date.and_hms_opt(h, m, 0)?,
+        "second" => date.and_hms_opt(h, m, s)?,
+        _ => return None,
+    };
+    Some(Utc.from_utc_datetime(&start).into())
+}
+
+fn call(unstructed: &Unstructed, name: &str, args: &[Operand]) -> Option<Value> {
+    match (name, args) {
+        ("exists", [Operand::Field(field)]) => {
+            return Some(Value::Bool(lookup(unstructed, field).is_some()))
+        }
+        ("type", [arg]) => {
+            return Some(Value::String(
+                type_of(eval(unstructed, arg).as_ref()).to_string(),
+            ))
+        }
+        ("now", []) => return Some(Value::String(Utc::now().to_rfc3339())),
+        _ => {}
+    }
+
+    let values: Vec<Value> = args
+        .iter()
+        .map(|arg| eval(unstructed, arg).unwrap_or(Value::Null))
+        .collect();
+    match (name, values.as_slice()) {
+        ("lower", [Value::String(v)]) => Some(Value::String(v.to_lowercase())),
+        ("upper", [Value::String(v)]) => Some(Value::String(v.to_uppercase())),
+        ("trim", [Value::String(v)]) => Some(Value::String(v.trim().to_string())),
+        ("abs", [v]) => match number(v)? {
+            Num::Int(v) => v
+                .checked_abs()
+                .map(Num::Int)
+                .unwrap_or(Num::Float((v as f64).abs())),
+            Num::Float(v) => Num::Float(v.abs()),
+        }
+        .value(),
+        ("size", [Value::Array(items)]) => Some(Value::from(items.len())),
+        ("date_trunc", [Value::String(unit), v]) => Some(Value::String(
+            date_trunc(unit, datetime_of(v)?)?.to_rfc3339(),
+        )),
+        (name, _) if condition::function::is_builtin(name) => None,
+        (name, values) => Function::get_eval(name).ok()?(values),
+    }
+}
+
+// 条件中的函数都需要在 matchs 中有实现
+fn check(expr: &Expr) -> anyhow::Result<()> {
+    match expr {
+        Expr::And { lhs, rhs, .. } | Expr::Or { lhs, rhs, .. } => {
+            check(lhs)?;
+            check(rhs)
+        }
+        Expr::Not { expr, .. } | Expr::Any { expr, .. } | Expr::All { expr, .. } => check(expr),
+        Expr::Compare { lhs, rhs, .. } => {
+            check_operand(lhs)?;
+            check_operand(rhs)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_operand(operand: &Operand) -> anyhow::Result<()> {
+    match operand {
+        Operand::Arith { lhs, rhs, .. } => {
+            check_operand(lhs)?;
+            check_operand(rhs)
+        }
+        Operand::Call { name, args } => {
+            if !condition::function::is_builtin(name) {
+                Function::get_eval(name)?;
+            }
+            args.iter().try_for_each(check_operand)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn filter(unstructed: &Unstructed, expr: &Expr, regexes: &Regexes) -> bool {
     match expr {
-        Expr::And { lhs, rhs, .. } => return filter(unstructed, lhs) && filter(unstructed, rhs),
-        Expr::Or { lhs, rhs, .. } => return filter(unstructed, lhs) || filter(unstructed, rhs),
+        Expr::And { lhs, rhs, .. } => {
+            return filter(unstructed, lhs, regexes) && filter(unstructed, rhs, regexes)
+        }
+        Expr::Or { lhs, rhs, .. } => {
+            return filter(unstructed, lhs, regexes) || filter(unstructed, rhs, regexes)
+        }
+        Expr::Not { expr, .. } => !filter(unstructed, expr, regexes),
         Expr::Eq { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Text(t) => {
                         if let Value::String(s) = s {
@@ -53,6 +502,12 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                         }
                         return false;
                     }
+                    condition::Value::DateTime(t) => {
+                        return datetime_of(s).is_some_and(|s| s.eq(t));
+                    }
+                    condition::Value::Field(other) => {
+                        return compare_field(unstructed, s, other).is_some_and(|o| o.is_eq());
+                    }
                     _ => return false,
                 }
             }
@@ -60,7 +515,7 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::Ne { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Text(t) => {
                         if let Value::String(s) = s {
@@ -80,6 +535,12 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                         }
                         return false;
                     }
+                    condition::Value::DateTime(t) => {
+                        return datetime_of(s).is_some_and(|s| s.ne(t));
+                    }
+                    condition::Value::Field(other) => {
+                        return compare_field(unstructed, s, other).is_some_and(|o| o.is_ne());
+                    }
                     _ => return false,
                 }
             }
@@ -87,7 +548,7 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::Gt { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Number(t) => {
                         if let Value::Number(s) = s {
@@ -101,6 +562,12 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                         }
                         return false;
                     }
+                    condition::Value::DateTime(t) => {
+                        return datetime_of(s).is_some_and(|s| s.gt(t));
+                    }
+                    condition::Value::Field(other) => {
+                        return compare_field(unstructed, s, other).is_some_and(|o| o.is_gt());
+                    }
                     _ => return false,
                 }
             }
@@ -108,7 +575,7 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::Gte { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Number(t) => {
                         if let Value::Number(s) = s {
@@ -122,6 +589,12 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                         }
                         return false;
                     }
+                    condition::Value::DateTime(t) => {
+                        return datetime_of(s).is_some_and(|s| s.ge(t));
+                    }
+                    condition::Value::Field(other) => {
+                        return compare_field(unstructed, s, other).is_some_and(|o| o.is_ge());
+                    }
                     _ => return false,
                 }
             }
@@ -129,7 +602,7 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::Lt { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Number(t) => {
                         if let Value::Number(s) = s {
@@ -143,6 +616,12 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                         }
                         return false;
                     }
+                    condition::Value::DateTime(t) => {
+                        return datetime_of(s).is_some_and(|s| s.lt(t));
+                    }
+                    condition::Value::Field(other) => {
+                        return compare_field(unstructed, s, other).is_some_and(|o| o.is_lt());
+                    }
                     _ => return false,
                 }
             }
@@ -150,7 +629,7 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::Lte { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Number(t) => {
                         if let Value::Number(s) = s {
@@ -164,6 +643,12 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                         }
                         return false;
                     }
+                    condition::Value::DateTime(t) => {
+                        return datetime_of(s).is_some_and(|s| s.le(t));
+                    }
+                    condition::Value::Field(other) => {
+                        return compare_field(unstructed, s, other).is_some_and(|o| o.is_le());
+                    }
                     _ => return false,
                 }
             }
@@ -171,15 +656,11 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::Like { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Text(t) => {
                         if let Value::String(s) = s {
-                            if let Ok(r) = regex::Regex::new(t) {
-                                return r.is_match(s);
-                            } else {
-                                return false;
-                            }
+                            return regexes.is_match(&MatchOp::Regex, t, "", s) == Some(true);
                         }
                         return false;
                     }
@@ -190,15 +671,11 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
             return false;
         }
         Expr::NotLike { field, value, .. } => {
-            if let Some(s) = unstructed.0.get(field) {
+            if let Some(s) = lookup(unstructed, field) {
                 match value {
                     condition::Value::Text(t) => {
                         if let Value::String(s) = s {
-                            if let Ok(r) = regex::Regex::new(t) {
-                                return !r.is_match(s);
-                            } else {
-                                return false;
-                            }
+                            return regexes.is_match(&MatchOp::Regex, t, "", s) == Some(false);
                         }
                         return false;
                     }
@@ -208,76 +685,23 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
 
             return false;
         }
-        Expr::In { field, value, .. } => {
-            if let condition::Value::List(condition_list) = value {
-                if let Some(s) = unstructed.0.get(field) {
-                    match s {
-                        Value::Number(s) => {
-                            for item in condition_list {
-                                if let condition::Value::Number(v) = item {
-                                    if !v.eq(s) {
-                                        continue;
-                                    }
-                                    return true;
-                                }
-                            }
-                        }
-                        Value::String(s) => {
-                            for item in condition_list {
-                                if let condition::Value::Text(v) = item {
-                                    if !v.eq(s) {
-                                        continue;
-                                    }
-                                    return true;
-                                }
-                            }
-                        }
-                        _ => return false,
-                    }
-                }
-                return false;
-            }
-
-            return false;
-        }
-        Expr::NotIn { field, value, .. } => {
-            if let condition::Value::List(condition_list) = value {
-                if let Some(s) = unstructed.0.get(field) {
-                    match s {
-                        Value::Number(s) => {
-                            let mut hits = vec![];
-                            for item in condition_list {
-                                if let condition::Value::Number(v) = item {
-                                    hits.push(!v.eq(s));
-                                }
-                            }
-                            return hits.iter().fold(true, |acc, e| (acc == *e) && (*e == true));
-                        }
-                        Value::String(s) => {
-                            let mut hits = vec![];
-                            for item in condition_list {
-                                if let condition::Value::Text(v) = item {
-                                    hits.push(!v.eq(s));
-                                }
-                            }
-                            return hits.iter().fold(true, |acc, e| (acc == *e) && (*e == true));
-                        }
-                        _ => return false,
-                    }
-                }
-                return false;
-            }
-
-            return false;
-        }
+        Expr::In { field, value, .. } => match value {
+            condition::Value::List(list) => list_contains(list, lookup(unstructed, field)),
+            _ => false,
+        },
+        // 与 mongodb 的 $nin 一致，列表中没有 null 时值为 null 或缺失的字段满足
+        Expr::NotIn { field, value, .. } => match value {
+            condition::Value::List(list) => !list_contains(list, lookup(unstructed, field)),
+            _ => false,
+        },
         Expr::IsNotNull { field, .. } => {
-            if unstructed.get_by_type::<Value>(field, Value::Null) != Value::Null {
+            if lookup(unstructed, field).is_some_and(|v| !v.is_null()) {
                 return true;
             }
             return false;
         }
         Expr::IsNull { field, .. } => {
-            if unstructed.get_by_type::<Value>(field, Value::Null) == Value::Null {
+            if lookup(unstructed, field).is_none_or(Value::is_null) {
                 return true;
             }
             return false;
@@ -289,11 +713,9 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
                 condition::Value::Number(v) => v.as_i64(),
                 _ => return false,
             };
-            let real = match unstructed.get_by_type::<Value>(field, Value::Array(vec![])) {
-                Value::String(v) => v.len() as i64,
-                Value::Array(v) => v.len() as i64,
-                Value::Object(v) => v.len() as i64,
-                _ => return false,
+            let real = match len_of(unstructed, field) {
+                Some(real) => real,
+                None => return false,
             };
             match cmp {
                 condition::Compare::Eq => len == Some(real),
@@ -306,18 +728,14 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
         }
         Expr::Belong { field, value, .. } => {
             if let condition::Value::List(rhs_list) = value {
-                let lhs_list = unstructed.get_by_type::<Vec<Value>>(&field, vec![]);
+                let lhs_list = elements(unstructed, field).cloned().unwrap_or_default();
                 if lhs_list.len() == 0 || rhs_list.len() == 0 {
                     return false;
                 }
 
                 let rhs_list = rhs_list
                     .iter()
-                    .map(|item| match item {
-                        condition::Value::Text(v) => Value::String(v.clone()),
-                        condition::Value::Number(v) => Value::Number(v.clone()),
-                        _ => Value::Null,
-                    })
+                    .map(|item| json_of(item).unwrap_or(Value::Null))
                     .collect::<Vec<_>>();
 
                 let mut hits = vec![];
@@ -337,18 +755,14 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
         }
         Expr::NoBelong { field, value, .. } => {
             if let condition::Value::List(rhs_list) = value {
-                let lhs_list = unstructed.get_by_type::<Vec<Value>>(&field, vec![]);
+                let lhs_list = elements(unstructed, field).cloned().unwrap_or_default();
                 if lhs_list.len() == 0 || rhs_list.len() == 0 {
                     return false;
                 }
 
                 let rhs_list = rhs_list
                     .iter()
-                    .map(|item| match item {
-                        condition::Value::Text(v) => Value::String(v.clone()),
-                        condition::Value::Number(v) => Value::Number(v.clone()),
-                        _ => Value::Null,
-                    })
+                    .map(|item| json_of(item).unwrap_or(Value::Null))
                     .collect::<Vec<_>>();
 
                 let mut hits = vec![];
@@ -366,12 +780,105 @@ fn filter(unstructed: &Unstructed, expr: &Expr) -> bool {
 
             return true;
         }
+        Expr::Compare { lhs, cmp, rhs, .. } => {
+            let ord = match (eval(unstructed, lhs), eval(unstructed, rhs)) {
+                (Some(l), Some(r)) => compare_values(&l, &r),
+                _ => None,
+            };
+            match ord {
+                Some(ord) => match cmp {
+                    condition::Compare::Eq => ord == Ordering::Equal,
+                    condition::Compare::Ne => ord != Ordering::Equal,
+                    condition::Compare::Gt => ord == Ordering::Greater,
+                    condition::Compare::Gte => ord != Ordering::Less,
+                    condition::Compare::Lt => ord == Ordering::Less,
+                    condition::Compare::Lte => ord != Ordering::Greater,
+                },
+                None => false,
+            }
+        }
+        Expr::Any { field, expr, .. } => elements(unstructed, field).is_some_and(|items| {
+            items
+                .iter()
+                .any(|item| element_matches(item, expr, regexes))
+        }),
+        Expr::All { field, expr, .. } => elements(unstructed, field).is_some_and(|items| {
+            !items.is_empty()
+                && items
+                    .iter()
+                    .all(|item| element_matches(item, expr, regexes))
+        }),
+        Expr::Match {
+            field,
+            op,
+            value,
+            flags,
+            ..
+        } => {
+            let (s, t) = match (lookup(unstructed, field), value) {
+                (Some(Value::String(s)), condition::Value::Text(t)) => (s, t),
+                _ => return false,
+            };
+            regexes.is_match(op, t, flags, s) == Some(true)
+        }
+        // 与 NotLike 一样只有字符串字段参与匹配
+        Expr::NotMatch {
+            field,
+            value,
+            flags,
+            ..
+        } => {
+            let (s, t) = match (lookup(unstructed, field), value) {
+                (Some(Value::String(s)), condition::Value::Text(t)) => (s, t),
+                _ => return false,
+            };
+            regexes.is_match(&MatchOp::Regex, t, flags, s) == Some(false)
+        }
+        Expr::Between {
+            field,
+            lower,
+            upper,
+            ..
+        } => {
+            let value = match lookup(unstructed, field) {
+                Some(value) => value,
+                None => return false,
+            };
+            // 与端点类型相同且在端点的指定一侧，包含端点时允许相等
+            let within = |bound: &condition::Bound, side: Ordering| {
+                json_of(bound.value())
+                    .and_then(|b| compare_values(value, &b))
+                    .is_some_and(|ord| ord == side || (ord.is_eq() && bound.is_inclusive()))
+            };
+            within(lower, Ordering::Greater) && within(upper, Ordering::Less)
+        }
+        Expr::Has { field, value, .. } => {
+            let values = match value {
+                condition::Value::List(values) if !values.is_empty() => values,
+                _ => return false,
+            };
+            // 与 mongodb 的 $all 一致，字段不是数组时按单个元素的数组处理
+            let items = match lookup(unstructed, field) {
+                Some(Value::Array(items)) => items.clone(),
+                Some(item) => vec![item.clone()],
+                None => return false,
+            };
+            values.iter().all(|v| {
+                json_of(v).is_some_and(|v| {
+                    items
+                        .iter()
+                        .any(|item| compare_values(item, &v) == Some(Ordering::Equal))
+                })
+            })
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::matchs;
+    use std::sync::Arc;
+
+    use super::{compile, matchs, Predicate, PREDICATES, PREDICATE_CACHE_CAPACITY};
     use crate::utils::from_str;
     use condition::yacc_parse as parse;
 
@@ -431,6 +938,35 @@ mod tests {
             }
             Err(e) => panic!("simulation data error: {}", e),
         }
+
+        // 空列表不匹配任何数据
+        let params = condition::Params::new().bind("ids", Vec::<i32>::new());
+        match matchs(
+            &mut datas.clone(),
+            condition::yacc_parse_with("a ~ :ids", &params).unwrap(),
+        ) {
+            Ok(r) => assert!(r.is_empty()),
+            Err(e) => panic!("simulation data error: {}", e),
+        }
+
+        // 与 mongodb 一致，null 匹配值为 null 或缺失的字段
+        let datas = vec![
+            from_str(r#"{"a":true}"#).unwrap(),
+            from_str(r#"{"a":false}"#).unwrap(),
+            from_str(r#"{"a":null}"#).unwrap(),
+            from_str(r#"{"b":1}"#).unwrap(),
+        ];
+        for (s, n) in [
+            ("a ~ (true)", 1),
+            ("a ~ (true, null)", 3),
+            ("a ~~ (true)", 3),
+            ("a ~~ (true, null)", 1),
+        ] {
+            match matchs(&mut datas.clone(), parse(s).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", s),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
     }
 
     #[test]
@@ -463,6 +999,17 @@ mod tests {
             }
             Err(e) => panic!("simulation data error: {}", e),
         }
+
+        // 字段缺失或不是字符串时不匹配，与 mongodb 的 $type: "string" 一致
+        let datas = vec![
+            from_str(r#"{"src":2}"#).unwrap(),
+            from_str(r#"{"name":1}"#).unwrap(),
+            from_str(r#"{"name":"alex"}"#).unwrap(),
+        ];
+        match matchs(&mut datas.clone(), parse("name !! '^b.'").unwrap()) {
+            Ok(r) => assert_eq!(r.len(), 1),
+            Err(e) => panic!("simulation data error: {}", e),
+        }
     }
 
     #[test]
@@ -540,6 +1087,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_not() {
+        let datas = vec![
+            from_str(r#"{"name":"bobo","src":2}"#).unwrap(),
+            from_str(r#"{"name":"bill","src":3}"#).unwrap(),
+            from_str(r#"{"name":"alex","src":3}"#).unwrap(),
+        ];
+
+        // where not (name like '^a' or src=2)
+        match matchs(
+            &mut datas.clone(),
+            parse("!(name ! '^a.' || src=2)").unwrap(),
+        ) {
+            Ok(r) => {
+                if r.len() != 1 || r[0].get("name") != "bill" {
+                    panic!("Inconsistent expected results")
+                }
+            }
+            Err(e) => panic!("simulation data error: {}", e),
+        }
+
+        // where not src=3 or name='alex'
+        match matchs(
+            &mut datas.clone(),
+            parse("NOT src=3 || name='alex'").unwrap(),
+        ) {
+            Ok(r) => {
+                if r.len() != 2 {
+                    panic!("Inconsistent expected results")
+                }
+            }
+            Err(e) => panic!("simulation data error: {}", e),
+        }
+    }
+
     #[test]
     fn test_bool() {
         let datas = vec![
@@ -647,6 +1229,13 @@ mod tests {
             Err(e) => panic!("simulation data error: {}", e),
         }
 
+        // 字符串按字符计数
+        let datas = vec![from_str(r#"{"name":"张三丰"}"#).unwrap()];
+        match matchs(&mut datas.clone(), parse(r#"len(name) = 3"#).unwrap()) {
+            Ok(r) => assert_eq!(r.len(), 1),
+            Err(e) => panic!("simulation data error: {}", e),
+        }
+
         let datas = vec![from_str(r#"{"name":"bobo","obj":{"a":1}}"#).unwrap()];
         // where len(obj) = 1
         match matchs(&mut datas.clone(), parse(r#"len(obj) = 1 "#).unwrap()) {
@@ -861,4 +1450,332 @@ mod tests {
             Err(e) => panic!("simulation data error: {}", e),
         }
     }
+
+    #[test]
+    fn test_datetime() {
+        let datas = vec![
+            from_str(r#"{"name":"a","t":"2022-06-13T16:00:00+08:00"}"#).unwrap(),
+            from_str(r#"{"name":"b","t":"2022-06-13 09:00:00"}"#).unwrap(),
+            from_str(r#"{"name":"c","t":{"$date":{"$numberLong":"1655114400000"}}}"#).unwrap(),
+            from_str(r#"{"name":"d","t":"not a date"}"#).unwrap(),
+        ];
+
+        // a: 08:00Z b: 09:00Z c: 10:00Z
+        let cases = [
+            ("t = 2022-06-13T08:00:00Z", 1),
+            ("t > 2022-06-13T08:00:00Z", 2),
+            ("t >= @1655107200 && t < 2022-06-13T18:00:00+08:00", 2),
+            ("t != 2022-06-13T08:00:00Z", 2),
+            ("t ~ (2022-06-13T08:00:00Z,2022-06-13T18:00:00+08:00)", 2),
+            ("t ~~ (2022-06-13T08:00:00Z,2022-06-13T18:00:00+08:00)", 2),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_compare() {
+        let datas = vec![
+            from_str(r#"{"name":"a","used":3,"quota":5,"created_at":"2022-06-13T16:00:00+08:00","updated_at":"2022-06-13 09:00:00"}"#).unwrap(),
+            from_str(r#"{"name":"b","used":5,"quota":5,"created_at":"2022-06-13T08:00:00Z","updated_at":"2022-06-13T08:00:00Z"}"#).unwrap(),
+            from_str(r#"{"name":"c","used":7,"quota":"5"}"#).unwrap(),
+        ];
+
+        let cases = [
+            ("used < quota", 1),
+            ("used <= quota", 2),
+            ("used = quota", 1),
+            ("used != quota", 1),
+            ("updated_at > created_at", 1),
+            ("updated_at >= created_at", 2),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_arith() {
+        let datas = vec![
+            from_str(r#"{"name":"a","price":12,"qty":9,"tags":["x","y"],"limit":2}"#).unwrap(),
+            from_str(r#"{"name":"b","price":2.5,"qty":40,"tags":[],"limit":2}"#).unwrap(),
+            from_str(r#"{"name":"c","price":9007199254740993,"qty":0,"tags":"xyz","limit":0}"#)
+                .unwrap(),
+            from_str(r#"{"name":"d","price":"12","qty":9}"#).unwrap(),
+        ];
+
+        let cases = [
+            ("price * qty >= 100", 2),
+            ("price * qty = 108", 1),
+            ("len(tags) + 1 > limit", 2),
+            ("qty % 4 = 1", 2),
+            ("qty / 2 = 4.5", 2),
+            ("qty / 4 = 10", 1),
+            // 超出 f64 精度的整数仍然精确比较
+            ("price + 1 = 9007199254740994", 1),
+            ("price - 1 > 9007199254740991", 1),
+            // 除数为 0 时不匹配
+            ("price / qty > 0", 2),
+            ("price % qty >= 0", 2),
+            ("1 < qty - 8", 1),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_function() {
+        let datas = vec![
+            from_str(r#"{"name":" Bill ","a":-3,"b":1,"tags":["x"],"t":"2022-06-13T16:30:00+08:00","n":null}"#).unwrap(),
+            from_str(r#"{"name":"BOB","a":"3","tags":"x","t":"2022-06-14T00:00:00Z"}"#).unwrap(),
+        ];
+
+        let cases = [
+            ("trim(lower(name)) = 'bill'", 1),
+            ("upper(name) = 'BOB'", 1),
+            ("exists(n) = true", 1),
+            ("exists(b) = false", 1),
+            ("abs(a) = 3", 1),
+            ("abs(a - b) > 3", 1),
+            ("size(tags) = 1", 1),
+            ("type(a) = 'number' || type(a) = 'string'", 2),
+            ("type(n) = 'null' && type(x) = 'missing'", 1),
+            ("date_trunc('day', t) = 2022-06-13T00:00:00Z", 1),
+            ("date_trunc('month', t) = 2022-06-01T00:00:00Z", 2),
+            ("date_trunc('week', t) = 2022-06-12T00:00:00Z", 2),
+            ("t < now()", 2),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+
+        crate::utils::Function::new("twice", 1, 1)
+            .eval(|args| Some((args[0].as_i64()? * 2).into()))
+            .register()
+            .unwrap();
+        match matchs(&mut datas.clone(), parse("twice(b) = 2").unwrap()) {
+            Ok(r) => assert_eq!(r.len(), 1),
+            Err(e) => panic!("simulation data error: {}", e),
+        }
+
+        // 没有 matchs 实现的函数返回错误
+        crate::utils::Function::new("score_only_in_mongo", 1, 1)
+            .mongo(|args| Ok(args[0].clone()))
+            .register()
+            .unwrap();
+        match matchs(
+            &mut datas.clone(),
+            parse("score_only_in_mongo(b) = 2").unwrap(),
+        ) {
+            Ok(r) => panic!("unexpected result {:?}", r),
+            Err(e) => assert!(e.to_string().contains("not supported by matchs"), "{}", e),
+        }
+    }
+
+    #[test]
+    fn test_elem() {
+        let datas = vec![
+            from_str(r#"{"name":"a","tags":["x","y"],"lines":[{"price":12,"qty":2},{"price":5,"qty":1}]}"#).unwrap(),
+            from_str(r#"{"name":"b","tags":["y"],"lines":[{"price":20,"qty":1},{"price":15,"qty":3}]}"#).unwrap(),
+            from_str(r#"{"name":"c","tags":"x","lines":[]}"#).unwrap(),
+            from_str(r#"{"name":"d","order":{"lines":[{"price":11,"qty":2}]}}"#).unwrap(),
+        ];
+
+        let cases = [
+            ("any(lines, price > 10 && qty > 1)", 2),
+            ("any(lines, price > 10) && any(lines, qty > 1)", 2),
+            ("all(lines, price > 10)", 1),
+            ("all(lines, !(qty = 3))", 1),
+            ("any(order.lines, price > 10 && qty > 1)", 1),
+            ("tags has 'x'", 2),
+            ("tags has ('x','y')", 1),
+            ("tags has 'z'", 0),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_between() {
+        let datas = vec![
+            from_str(r#"{"name":"a","age":18,"t":"2022-06-13T00:00:00Z"}"#).unwrap(),
+            from_str(r#"{"name":"b","age":30.5,"t":"2022-06-13T12:00:00Z"}"#).unwrap(),
+            from_str(r#"{"name":"c","age":60,"t":"2022-06-14T00:00:00Z"}"#).unwrap(),
+            from_str(r#"{"name":"d","age":"30"}"#).unwrap(),
+        ];
+
+        let cases = [
+            ("age between [18, 60)", 2),
+            ("age between (18, 60)", 1),
+            ("age between [18, 60]", 3),
+            ("age between (18, 60]", 2),
+            ("name between ['a', 'c')", 2),
+            ("t between [2022-06-13T00:00:00Z, 2022-06-14T00:00:00Z)", 2),
+            ("t between (2022-06-13T08:00:00+08:00, @1655164800]", 2),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nested() {
+        let datas = vec![
+            from_str(r#"{"user":{"name":"bill","age":18,"tags":[1,2],"memo":null}}"#).unwrap(),
+            from_str(r#"{"user":{"name":"alex","age":30,"tags":[3]}}"#).unwrap(),
+            from_str(r#"{"user":1}"#).unwrap(),
+        ];
+
+        // 所有操作符都按 a.b 查找嵌套字段，与 mongodb 一致
+        let cases = [
+            ("user.name = 'bill'", 1),
+            ("user.name != 'bill'", 1),
+            ("user.age > 18", 1),
+            ("user.age >= 18", 2),
+            ("user.age < 30", 1),
+            ("user.age <= 30", 2),
+            ("user.name ! '^b'", 1),
+            ("user.name !! '^b'", 1),
+            ("user.name ~ ('bill','x')", 1),
+            ("user.name ~~ ('bill')", 2),
+            ("user.memo ^ null", 3),
+            ("user.name ^^ null", 2),
+            ("len(user.name) = 4", 2),
+            ("user.tags << (1,2,3)", 2),
+            ("user.tags >> (1,2)", 1),
+            ("user.age = user.age", 2),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let datas = vec![
+            from_str(r#"{"name":"Bill.Gates","mail":"bill@A.COM","memo":"x\nfoo bar"}"#).unwrap(),
+            from_str(r#"{"name":"billxgates","mail":"bill@b.com","memo":"foo"}"#).unwrap(),
+            from_str(r#"{"name":"a.(b","mail":1}"#).unwrap(),
+        ];
+
+        let cases = [
+            ("name contains '.'", 2),
+            ("name contains 'l.g'", 0),
+            ("name contains 'l.g' i", 1),
+            ("name contains 'a.(b'", 1),
+            ("name startswith 'bill'", 1),
+            ("name startswith 'bill' i", 2),
+            ("mail endswith '.com'", 1),
+            ("mail endswith '.com' i", 2),
+            ("name ieq 'bill.gates'", 1),
+            ("name ! 'bill.gates'", 1),
+            ("name ! '^bill.gates$' i", 2),
+            ("name !! '^bill' i", 1),
+            // 与 !! 一样跳过非字符串字段
+            ("mail !! 'x' i", 2),
+            ("none !! 'x' i", 0),
+            ("memo ! '^foo'", 1),
+            ("memo ! '^foo' m", 2),
+            // 多行选项不影响 startswith
+            ("memo startswith 'foo' m", 1),
+        ];
+        for (sym, n) in cases {
+            match matchs(&mut datas.clone(), parse(sym).unwrap()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", sym),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        use condition::field;
+
+        let datas = vec![
+            from_str(r#"{"name":"bill","age":3,"tags":["a","b"]}"#).unwrap(),
+            from_str(r#"{"name":"alex","age":5,"tags":["b"]}"#).unwrap(),
+        ];
+        let cases = [
+            (field("age").gt(3), 1),
+            (
+                field("name")
+                    .is_in(["bill", "alex"])
+                    .and(field("tags").has(["b"])),
+                2,
+            ),
+            (!field("tags").len().gt(1), 1),
+            (field("name").starts_with("b").or(field("age").eq(5)), 2),
+        ];
+        for (expr, n) in cases {
+            match matchs(&mut datas.clone(), expr.clone()) {
+                Ok(r) => assert_eq!(r.len(), n, "{}", expr),
+                Err(e) => panic!("simulation data error: {}", e),
+            }
+        }
+        assert!(datas[0].match_by_expr(field("age").eq(3)).unwrap());
+    }
+
+    #[test]
+    fn test_predicate() {
+        let datas = vec![
+            from_str(r#"{"name":"bill","items":[{"tag":"Ab"}]}"#).unwrap(),
+            from_str(r#"{"name":"alex","items":[{"tag":"c"}]}"#).unwrap(),
+        ];
+
+        let predicate =
+            Predicate::parse("name ! '^b' || any(items, tag contains 'x' i) || name !! '['")
+                .unwrap();
+        // 相同的正则只编译一次，非法正则也记录下来
+        assert_eq!(predicate.0 .1 .0.len(), 3);
+        assert!(predicate.matches(&datas[0]));
+        assert!(!predicate.matches(&datas[1]));
+
+        // 在线程间共享
+        let handles = (0..4)
+            .map(|_| {
+                let (predicate, datas) = (predicate.clone(), datas.clone());
+                std::thread::spawn(move || datas.iter().filter(|d| predicate.matches(d)).count())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 1);
+        }
+
+        // 相同的原文复用缓存
+        let src = "name = 'test_predicate_cache'";
+        let a = compile(src).unwrap();
+        assert!(Arc::ptr_eq(&a.0, &compile(src).unwrap().0));
+        assert!(compile("name = = 1").is_err());
+
+        // 超出容量后淘汰最久未使用的条件
+        for i in 0..=PREDICATE_CACHE_CAPACITY {
+            compile(&format!("n = {}", i)).unwrap();
+        }
+        assert!(!Arc::ptr_eq(&a.0, &compile(src).unwrap().0));
+        assert!(PREDICATES.lock().unwrap().items.len() <= PREDICATE_CACHE_CAPACITY);
+    }
 }
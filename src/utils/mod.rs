@@ -1,16 +1,18 @@
-use condition::yacc_parse as parse;
+use condition::Expr;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{json, Map, Value};
 
 use self::value::map_get;
 
 pub mod dict;
+pub mod function;
 pub mod local;
 pub mod matchs;
 pub mod retry;
 pub mod time;
 pub mod value;
 
+pub use function::Function;
 pub use local::{ErrorLocation, Location};
 
 #[macro_export]
@@ -69,11 +71,14 @@ impl Unstructed {
         map_get::<T>(&self.0, key, default)
     }
 
+    // 相同的条件原文复用进程内缓存的已编译条件
     pub fn match_by_predicate(&self, predicate: &str) -> anyhow::Result<bool> {
-        if matchs::matchs(&mut vec![self.clone()], parse(predicate)?)?.len() > 0 {
-            return Ok(true);
-        }
-        Ok(false)
+        Ok(matchs::compile(predicate)?.matches(self))
+    }
+
+    // 使用代码中构造的表达式匹配，不经过字符串解析
+    pub fn match_by_expr(&self, expr: Expr) -> anyhow::Result<bool> {
+        Ok(matchs::Predicate::new(expr)?.matches(self))
     }
 
     pub fn cut(&self, keys: Vec<String>) -> Unstructed {
@@ -251,6 +256,13 @@ mod tests {
             &[("a=1", "a 必须等于1", true), ("a!=1", "a 必须等于1", false)],
         )
         .unwrap();
+
+        // 字段间比较
+        validate(&item, &[("b > a", "b 必须大于 a", true)]).unwrap();
+        assert_eq!(
+            validate(&item, &[("a >= b", "a 必须大于等于 b", true)]),
+            Err("a 必须大于等于 b".to_string())
+        );
     }
 
     #[test]